@@ -1,5 +1,6 @@
 use crate::helpers::parse_grid_from_file;
-use hashbrown::HashSet;
+use crate::helpers::render::{render, Frame, Output};
+use hashbrown::HashMap;
 
 use std::path::Path;
 
@@ -21,90 +22,176 @@ fn parse_from_file<T: AsRef<Path>>(filename: T) -> Input {
 }
 
 pub fn part_one(grid: &mut [Vec<usize>]) -> usize {
-    let mut flashes = 0;
-    for _step in 0..100 {
-        flashes += iterate_dem_octos(grid);
-        print_grid(grid);
-    }
+    let mut cavern = Cavern::from(&*grid);
+    let flashes = (0..100).map(|_| cavern.step()).sum();
+    cavern.write_to(grid);
     flashes
 }
 
-fn iterate_dem_octos(grid: &mut [Vec<usize>]) -> usize {
-    for y in 0..grid.len() {
-        for x in 0..grid[0].len() {
-            grid[y][x] += 1;
+/// The octopuses' energy levels in one flat array, with the neighbours of each worked out up front.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Cavern {
+    width: usize,
+    height: usize,
+    energy: Vec<u8>,
+    neighbours: Vec<Vec<usize>>,
+}
+
+/// The steps after which the cavern repeats itself: the state after `start` steps comes round again after
+/// every `period` more.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Cycle {
+    pub start: usize,
+    pub period: usize,
+}
+
+#[allow(dead_code)]
+impl Cavern {
+    pub fn new(width: usize, height: usize, energy: Vec<u8>) -> Self {
+        assert_eq!(
+            energy.len(),
+            width * height,
+            "You wot? That isn't {} by {}",
+            width,
+            height
+        );
+        let neighbours = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (y, x)))
+            .map(|(y, x)| {
+                (y.saturating_sub(1)..(y + 2).min(height))
+                    .flat_map(|other_y| {
+                        (x.saturating_sub(1)..(x + 2).min(width)).map(move |other_x| (other_y, other_x))
+                    })
+                    .filter(|&other| other != (y, x))
+                    .map(|(other_y, other_x)| other_y * width + other_x)
+                    .collect()
+            })
+            .collect();
+        Cavern {
+            width,
+            height,
+            energy,
+            neighbours,
         }
     }
 
-    let mut flashed: HashSet<(usize, usize)> = HashSet::new();
-    for y in 0..grid.len() {
-        for x in 0..grid[0].len() {
-            if grid[y][x] >= 10 {
-                flash_dem_neighbours(grid, (y, x), &mut flashed);
+    pub fn size(&self) -> usize {
+        self.energy.len()
+    }
+
+    pub fn energy(&self, (y, x): (usize, usize)) -> u8 {
+        self.energy[y * self.width + x]
+    }
+
+    /// Charges every octopus, then flashes each one that gets past 9 exactly once, returning how many did.
+    pub fn step(&mut self) -> usize {
+        let mut to_flash = vec![];
+        for (i, energy) in self.energy.iter_mut().enumerate() {
+            *energy += 1;
+            if *energy == 10 {
+                to_flash.push(i);
+            }
+        }
+        let mut flashes = 0;
+        while let Some(i) = to_flash.pop() {
+            flashes += 1;
+            for &neighbour in &self.neighbours[i] {
+                self.energy[neighbour] += 1;
+                if self.energy[neighbour] == 10 {
+                    to_flash.push(neighbour);
+                }
+            }
+        }
+        for energy in self.energy.iter_mut() {
+            if *energy > 9 {
+                *energy = 0;
             }
         }
+        flashes
+    }
+
+    /// The number of flashes on each step from now on.
+    pub fn flashes(mut self) -> impl Iterator<Item = usize> {
+        std::iter::from_fn(move || Some(self.step()))
+    }
+
+    /// Steps until `predicate` holds for the cavern and the number of flashes just made, returning how many
+    /// steps that took, or `None` if it doesn't happen within `limit` steps.
+    pub fn first_step_where<P>(&mut self, limit: usize, mut predicate: P) -> Option<usize>
+    where
+        P: FnMut(&Cavern, usize) -> bool,
+    {
+        (1..=limit).find(|_| {
+            let flashes = self.step();
+            predicate(self, flashes)
+        })
     }
 
-    for y in 0..grid.len() {
-        for x in 0..grid[0].len() {
-            if grid[y][x] >= 10 {
-                grid[y][x] = 0;
+    /// Steps until the cavern gets back to a state it has been in before. There are only so many states, so
+    /// this always finishes eventually, although for big caverns that could take a while.
+    pub fn find_cycle(&self) -> Cycle {
+        let mut cavern = self.clone();
+        let mut seen = HashMap::new();
+        for step in 0.. {
+            if let Some(start) = seen.insert(cavern.energy.clone(), step) {
+                return Cycle {
+                    start,
+                    period: step - start,
+                };
             }
+            cavern.step();
         }
+        unreachable!()
     }
-    flashed.len()
-}
 
-fn print_grid(grid: &[Vec<usize>]) {
-    for row in grid {
-        for val in row {
-            print!(" {:2} ", val);
+    fn write_to(&self, grid: &mut [Vec<usize>]) {
+        for (row, energies) in grid.iter_mut().zip(self.energy.chunks(self.width)) {
+            for (value, energy) in row.iter_mut().zip(energies) {
+                *value = *energy as usize;
+            }
         }
-        println!();
     }
-}
 
-fn flash_dem_neighbours(grid: &mut [Vec<usize>], (y, x): (usize, usize), flashed: &mut HashSet<(usize, usize)>) {
-    if flashed.contains(&(y, x)) {
-        return;
+    /// Octopuses that have just flashed are bright, the rest get bluer as they charge up.
+    fn frame(&self) -> Frame {
+        Frame::from_fn(self.width, self.height, |position| {
+            let energy = self.energy(position);
+            let glyph = std::char::from_digit(energy as u32, 10).unwrap_or('+');
+            if energy == 0 {
+                (glyph, [255, 255, 200])
+            } else {
+                let level = energy * 20;
+                (glyph, [level / 4, level / 2, 60 + level])
+            }
+        })
     }
-    flashed.insert((y, x));
-    let neighbours = get_neighbours(grid, (y, x));
-    for (other_y, other_x) in neighbours {
-        grid[other_y][other_x] += 1;
-        if grid[other_y][other_x] >= 10 {
-            flash_dem_neighbours(grid, (other_y, other_x), flashed);
-        }
+}
+
+impl From<&[Vec<usize>]> for Cavern {
+    fn from(grid: &[Vec<usize>]) -> Self {
+        let energy = grid.iter().flatten().map(|energy| *energy as u8).collect();
+        Cavern::new(grid[0].len(), grid.len(), energy)
     }
 }
 
-fn get_neighbours(grid: &[Vec<usize>], (y, x): (usize, usize)) -> impl Iterator<Item = (usize, usize)> {
-    let (y, x) = (y as i32, x as i32);
-    let max_x = grid[0].len() as i32;
-    let max_y = grid.len() as i32;
-    (-1..=1)
-        .flat_map(move |dy| {
-            (-1..=1).map(move |dx| {
-                if (dx == 0 && dy == 0) || dx + x < 0 || dx + x >= max_x || dy + y < 0 || dy + y >= max_y {
-                    None
-                } else {
-                    Some(((y + dy) as usize, (x + dx) as usize))
-                }
-            })
-        })
-        .flatten()
+/// Renders each step up to the first one where every octopus flashes at once.
+pub fn animate(output: &Output) -> Result<(), ()> {
+    let mut cavern = Cavern::from(parse_from_file("./inputs/day11.txt").as_slice());
+    let mut frames = vec![cavern.frame()];
+    cavern.first_step_where(1000, |cavern, flashes| {
+        frames.push(cavern.frame());
+        flashes == cavern.size()
+    });
+    render(frames, output).map_err(|error| println!("{}", error))
 }
 
 pub fn part_two(grid: &mut [Vec<usize>]) -> usize {
-    let mut step = 0;
-    let size = grid[0].len() * grid.len();
-    loop {
-        step += 1;
-        let flashes_this_time = iterate_dem_octos(grid);
-        if flashes_this_time == size {
-            break step;
-        }
-    }
+    let mut cavern = Cavern::from(&*grid);
+    let step = cavern
+        .first_step_where(usize::MAX, |cavern, flashes| flashes == cavern.size())
+        .unwrap();
+    cavern.write_to(grid);
+    step
 }
 
 #[cfg(test)]
@@ -120,10 +207,11 @@ mod tests {
     }
 
     #[test]
-    fn test_get_neighbours() {
-        let mut input = parse_grid_from_str(TEST_INPUT);
-        dbg!(get_neighbours(&mut input, (1, 1)).collect::<Vec<_>>());
-        assert_eq!(get_neighbours(&mut input, (2, 2)).count(), 8)
+    fn test_neighbours() {
+        let cavern = Cavern::from(parse_grid_from_str(TEST_INPUT).as_slice());
+        assert_eq!(cavern.neighbours[2 * 10 + 2].len(), 8);
+        assert_eq!(cavern.neighbours[0], vec![1, 10, 11]);
+        assert_eq!(cavern.neighbours[99].len(), 3);
     }
 
     #[test]
@@ -131,4 +219,55 @@ mod tests {
         let mut input = parse_grid_from_str(TEST_INPUT);
         assert_eq!(part_two(&mut input), 195);
     }
+
+    #[test]
+    fn test_small_example() {
+        let grid = parse_grid_from_str("11111\n19991\n19191\n19991\n11111");
+        let mut cavern = Cavern::from(grid.as_slice());
+        assert_eq!(cavern.step(), 9);
+        assert_eq!(
+            cavern,
+            Cavern::from(parse_grid_from_str("34543\n40004\n50005\n40004\n34543").as_slice())
+        );
+        assert_eq!(cavern.step(), 0);
+        assert_eq!(cavern.energy((2, 2)), 1);
+    }
+
+    #[test]
+    fn test_flash_series() {
+        let cavern = Cavern::from(parse_grid_from_str(TEST_INPUT).as_slice());
+        let series: Vec<usize> = cavern.flashes().take(10).collect();
+        assert_eq!(series.iter().sum::<usize>(), 204);
+        assert_eq!(series[0], 0);
+    }
+
+    #[test]
+    fn test_first_step_where_and_cycle() {
+        let mut cavern = Cavern::from(parse_grid_from_str(TEST_INPUT).as_slice());
+        assert_eq!(cavern.clone().first_step_where(100, |_, flashes| flashes > 30), Some(2));
+        assert_eq!(
+            cavern
+                .clone()
+                .first_step_where(10, |cavern, flashes| flashes == cavern.size()),
+            None
+        );
+        assert_eq!(cavern.find_cycle(), Cycle { start: 195, period: 10 });
+        assert_eq!(cavern.first_step_where(1000, |_, flashes| flashes == 100), Some(195));
+    }
+
+    #[test]
+    fn test_uneven_cavern() {
+        let mut cavern = Cavern::new(3, 1, vec![9, 8, 1]);
+        assert_eq!(cavern.step(), 2);
+        assert_eq!(cavern.energy((0, 2)), 3);
+        let Cycle { start, period } = cavern.find_cycle();
+        for _ in 0..start {
+            cavern.step();
+        }
+        let repeated = cavern.clone();
+        for _ in 0..period {
+            cavern.step();
+        }
+        assert_eq!(cavern, repeated);
+    }
 }
@@ -38,22 +38,303 @@ fn parse_from_str(input: &str) -> Input {
 }
 
 pub fn part_one(map: Input) -> usize {
-    let _visited: HashSet<String> = HashSet::new();
-    let result = get_paths_from("start", &map, HashSet::new());
-    result
-        .iter()
-        .filter(|path| path.iter().last().unwrap() == "end")
-        .count()
+    CaveSystem::from(&map)
+        .count_paths(VisitRule::ONCE)
+        .expect("Big caves next to each other make infinitely many paths") as usize
 }
 
 pub fn part_two(map: Input) -> usize {
-    let result = get_longer_paths_from("start", &map, HashMap::new());
-    result
-        .iter()
-        .filter(|path| path.iter().last().unwrap() == "end")
-        .count()
+    CaveSystem::from(&map)
+        .count_paths(VisitRule::ONE_TWICE)
+        .expect("Big caves next to each other make infinitely many paths") as usize
+}
+
+/// Each small cave may be visited `limit` times, apart from up to `exceptions` of them which may be
+/// visited once more. The start and end aren't held to the rule: paths can't go back to the start, and finish
+/// as soon as they reach the end.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct VisitRule {
+    pub limit: usize,
+    pub exceptions: usize,
+}
+
+impl VisitRule {
+    pub const ONCE: VisitRule = VisitRule {
+        limit: 1,
+        exceptions: 0,
+    };
+    pub const ONE_TWICE: VisitRule = VisitRule {
+        limit: 1,
+        exceptions: 1,
+    };
+}
+
+/// The caves numbered by name, with each small cave also given a slot in a packed set of visit counts.
+#[derive(Debug, Clone)]
+pub struct CaveSystem {
+    names: Vec<String>,
+    ids: HashMap<String, usize>,
+    neighbours: Vec<Vec<usize>>,
+    small_slots: Vec<Option<usize>>,
+    start: usize,
+    end: usize,
+}
+
+impl From<&Input> for CaveSystem {
+    fn from(map: &Input) -> Self {
+        let mut names: Vec<String> = map.keys().cloned().collect();
+        names.sort();
+        let ids: HashMap<String, usize> = names.iter().enumerate().map(|(id, name)| (name.clone(), id)).collect();
+        let neighbours = names
+            .iter()
+            .map(|name| map[name].iter().map(|other| ids[other]).collect())
+            .collect();
+        let mut small_count = 0;
+        let small_slots = names
+            .iter()
+            .map(|name| {
+                if is_big(name) {
+                    None
+                } else {
+                    small_count += 1;
+                    Some(small_count - 1)
+                }
+            })
+            .collect();
+        let start = *ids.get("start").expect("You wot? There's no start");
+        let end = *ids.get("end").expect("You wot? There's no end");
+        CaveSystem {
+            names,
+            ids,
+            neighbours,
+            small_slots,
+            start,
+            end,
+        }
+    }
+}
+
+fn is_big(name: &str) -> bool {
+    name.chars().next().unwrap().is_uppercase()
+}
+
+/// The visit counts of every small cave, along with how many caves have used up an exception.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+struct Visits {
+    counts: Counts,
+    exceptions: usize,
+}
+
+/// Packed into one number with `bits` bits for each small cave, or one number each when there are too many
+/// small caves for that.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+enum Counts {
+    Packed(u128),
+    Wide(Vec<usize>),
 }
 
+#[allow(dead_code)]
+impl CaveSystem {
+    pub fn id(&self, name: &str) -> Option<usize> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, id: usize) -> &str {
+        &self.names[id]
+    }
+
+    pub fn is_small(&self, id: usize) -> bool {
+        self.small_slots[id].is_some()
+    }
+
+    pub fn neighbours(&self, id: usize) -> &[usize] {
+        &self.neighbours[id]
+    }
+
+    /// Counts the paths from start to end that keep to `rule`, or `None` if there are infinitely many
+    /// because two big caves are joined.
+    pub fn count_paths(&self, rule: VisitRule) -> Option<u128> {
+        let bits = self.count_bits(rule)?;
+        let mut memo = HashMap::new();
+        Some(self.count_from(self.start, self.no_visits(bits), rule, bits, &mut memo))
+    }
+
+    /// Lists the paths that keep to `rule` as cave ids, depth first in the order the input gives each
+    /// cave's neighbours, or `None` if there are infinitely many.
+    pub fn paths(&self, rule: VisitRule) -> Option<Paths<'_>> {
+        let bits = self.count_bits(rule)?;
+        Some(Paths {
+            caves: self,
+            rule,
+            bits,
+            stack: vec![(vec![self.start], self.no_visits(bits))],
+        })
+    }
+
+    /// A path in the puzzle's `start,A,b,end` form.
+    pub fn format_path(&self, path: &[usize]) -> String {
+        path.iter().map(|id| self.name(*id)).collect::<Vec<_>>().join(",")
+    }
+
+    /// The caves as an undirected Graphviz graph, with big caves as boxes, small caves as circles, and the
+    /// start and end as coloured double circles.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("graph caves {\n");
+        for (id, name) in self.names.iter().enumerate() {
+            let style = if id == self.start {
+                "shape=doublecircle, style=filled, fillcolor=palegreen"
+            } else if id == self.end {
+                "shape=doublecircle, style=filled, fillcolor=lightcoral"
+            } else if self.is_small(id) {
+                "shape=circle"
+            } else {
+                "shape=box, style=filled, fillcolor=lightblue"
+            };
+            dot += &format!("    \"{}\" [{}];\n", name, style);
+        }
+        for (id, neighbours) in self.neighbours.iter().enumerate() {
+            for &other in neighbours.iter().filter(|other| id < **other) {
+                dot += &format!("    \"{}\" -- \"{}\";\n", self.names[id], self.names[other]);
+            }
+        }
+        dot + "}\n"
+    }
+
+    /// How many bits each small cave's visit count needs under `rule`, or `None` if two big caves are joined.
+    fn count_bits(&self, rule: VisitRule) -> Option<usize> {
+        let big_neighbours = (0..self.names.len())
+            .any(|id| !self.is_small(id) && self.neighbours[id].iter().any(|other| !self.is_small(*other)));
+        if big_neighbours {
+            return None;
+        }
+        Some((usize::BITS - (rule.limit + 1).leading_zeros()) as usize)
+    }
+
+    /// Packs the counts if every small cave's count fits in `bits` bits of a `u128`.
+    fn no_visits(&self, bits: usize) -> Visits {
+        let small_caves = self.small_slots.iter().flatten().count();
+        let counts = if bits * small_caves <= 128 {
+            Counts::Packed(0)
+        } else {
+            Counts::Wide(vec![0; small_caves])
+        };
+        Visits { counts, exceptions: 0 }
+    }
+
+    /// Records a visit to the cave, if the rule allows it.
+    fn visit(&self, visits: &Visits, id: usize, rule: VisitRule, bits: usize) -> Option<Visits> {
+        let slot = match self.small_slots[id] {
+            Some(slot) => slot,
+            None => return Some(visits.clone()),
+        };
+        let count = match &visits.counts {
+            Counts::Packed(counts) => ((counts >> (slot * bits)) & ((1 << bits) - 1)) as usize,
+            Counts::Wide(counts) => counts[slot],
+        };
+        let exceptions = if count < rule.limit {
+            visits.exceptions
+        } else if count == rule.limit && visits.exceptions < rule.exceptions {
+            visits.exceptions + 1
+        } else {
+            return None;
+        };
+        let counts = match &visits.counts {
+            Counts::Packed(counts) => Counts::Packed(counts + (1 << (slot * bits))),
+            Counts::Wide(counts) => {
+                let mut counts = counts.clone();
+                counts[slot] += 1;
+                Counts::Wide(counts)
+            }
+        };
+        Some(Visits { counts, exceptions })
+    }
+
+    fn count_from(
+        &self,
+        id: usize,
+        visits: Visits,
+        rule: VisitRule,
+        bits: usize,
+        memo: &mut HashMap<(usize, Visits), u128>,
+    ) -> u128 {
+        if let Some(count) = memo.get(&(id, visits.clone())) {
+            return *count;
+        }
+        let mut count = 0;
+        for &other in &self.neighbours[id] {
+            if other == self.start {
+                continue;
+            }
+            if other == self.end {
+                count += 1;
+            } else if let Some(visits) = self.visit(&visits, other, rule, bits) {
+                count += self.count_from(other, visits, rule, bits, memo);
+            }
+        }
+        memo.insert((id, visits), count);
+        count
+    }
+}
+
+pub struct Paths<'a> {
+    caves: &'a CaveSystem,
+    rule: VisitRule,
+    bits: usize,
+    stack: Vec<(Vec<usize>, Visits)>,
+}
+
+impl Iterator for Paths<'_> {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let caves = self.caves;
+        while let Some((path, visits)) = self.stack.pop() {
+            let id = *path.last().unwrap();
+            if id == caves.end {
+                return Some(path);
+            }
+            for &other in caves.neighbours[id].iter().rev() {
+                let visits = if other == caves.start {
+                    None
+                } else if other == caves.end {
+                    Some(visits.clone())
+                } else {
+                    caves.visit(&visits, other, self.rule, self.bits)
+                };
+                if let Some(visits) = visits {
+                    let mut path = path.clone();
+                    path.push(other);
+                    self.stack.push((path, visits));
+                }
+            }
+        }
+        None
+    }
+}
+
+pub fn print_dot() -> Result<(), ()> {
+    let input = parse_from_file("./inputs/day12.txt");
+    print!("{}", CaveSystem::from(&input).to_dot());
+    Ok(())
+}
+
+/// Prints the paths for either part rather than counting them, stopping after `limit` if there is one.
+pub fn list_paths(part: usize, limit: Option<usize>) -> Result<(), ()> {
+    let input = parse_from_file("./inputs/day12.txt");
+    let caves = CaveSystem::from(&input);
+    let rule = if part == 1 {
+        VisitRule::ONCE
+    } else {
+        VisitRule::ONE_TWICE
+    };
+    let paths = caves.paths(rule).ok_or(())?;
+    for path in paths.take(limit.unwrap_or(usize::MAX)) {
+        println!("{}", caves.format_path(&path));
+    }
+    Ok(())
+}
+
+#[allow(dead_code)]
 fn can_visit(node: &str, visited: &mut HashMap<String, usize>) -> bool {
     if node == "start" {
         return *visited.entry(node.to_string()).or_insert(0) < 1;
@@ -67,6 +348,7 @@ fn can_visit(node: &str, visited: &mut HashMap<String, usize>) -> bool {
             .all(|(_k, v)| *v <= 1))
 }
 
+#[allow(dead_code)]
 fn get_longer_paths_from(node: &str, map: &Input, mut visited: HashMap<String, usize>) -> Vec<VecDeque<String>> {
     if node == "end" {
         return vec![vec!["end".to_string()].into()];
@@ -96,6 +378,7 @@ fn get_longer_paths_from(node: &str, map: &Input, mut visited: HashMap<String, u
         .collect()
 }
 
+#[allow(dead_code)]
 fn get_paths_from(node: &str, map: &Input, visited: HashSet<String>) -> Vec<VecDeque<String>> {
     if node == "end" {
         return vec![vec!["end".to_string()].into()];
@@ -128,6 +411,8 @@ fn get_paths_from(node: &str, map: &Input, visited: HashSet<String>) -> Vec<VecD
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
 
     const TEST_INPUT: &str = include_str!("../../test_inputs/day12.txt");
 
@@ -151,4 +436,173 @@ mod tests {
         let map = parse_from_str(TEST_INPUT);
         assert_eq!(get_longer_paths_from("start", &map, HashMap::new()).len(), 36);
     }
+
+    #[test]
+    fn test_counting() {
+        let map = parse_from_str(TEST_INPUT);
+        assert_eq!(part_one(map.clone()), 10);
+        assert_eq!(part_two(map), 36);
+        let larger_map = parse_from_file("./test_inputs/day12_larger.txt");
+        assert_eq!(part_one(larger_map.clone()), 226);
+        assert_eq!(part_two(larger_map), 3509);
+    }
+
+    /// A random cave system where no two big caves are joined, always with a way out of start and into end.
+    fn generate(rng: &mut StdRng, small: usize, big: usize, joins: usize) -> String {
+        let mut names: Vec<String> = vec!["start".to_string(), "end".to_string()];
+        names.extend((0..small).map(|i| format!("s{}", i)));
+        names.extend((0..big).map(|i| format!("B{}", i)));
+        let mut lines = vec![
+            format!("start-{}", names[rng.gen_range(2..names.len())]),
+            format!("{}-end", names[rng.gen_range(2..names.len())]),
+        ];
+        while lines.len() < joins {
+            let (a, b) = (
+                &names[rng.gen_range(0..names.len())],
+                &names[rng.gen_range(0..names.len())],
+            );
+            if a != b && !(is_big(a) && is_big(b)) {
+                lines.push(format!("{}-{}", a, b));
+            }
+        }
+        lines.sort();
+        lines.dedup();
+        lines.join("\n")
+    }
+
+    #[test]
+    fn test_counting_against_enumeration() {
+        let mut rng = StdRng::seed_from_u64(20);
+        for _ in 0..20 {
+            let map = parse_from_str(&generate(&mut rng, 4, 2, 12));
+            let caves = CaveSystem::from(&map);
+            let paths = get_paths_from("start", &map, HashSet::new());
+            let ending =
+                |paths: &Vec<VecDeque<String>>| paths.iter().filter(|path| path.back().unwrap() == "end").count();
+            assert_eq!(caves.count_paths(VisitRule::ONCE), Some(ending(&paths) as u128));
+            let paths = get_longer_paths_from("start", &map, HashMap::new());
+            assert_eq!(caves.count_paths(VisitRule::ONE_TWICE), Some(ending(&paths) as u128));
+        }
+    }
+
+    #[test]
+    fn test_general_rules() {
+        let caves = CaveSystem::from(&parse_from_str("start-A\nA-b\nA-end"));
+        let counts: Vec<u128> = (0..4)
+            .map(|limit| caves.count_paths(VisitRule { limit, exceptions: 0 }).unwrap())
+            .collect();
+        assert_eq!(counts, vec![1, 2, 3, 4]);
+        assert_eq!(
+            caves.count_paths(VisitRule {
+                limit: 2,
+                exceptions: 1
+            }),
+            Some(4)
+        );
+
+        let caves = CaveSystem::from(&parse_from_str("start-A\nA-b\nA-c\nA-end"));
+        assert_eq!(
+            caves.count_paths(VisitRule {
+                limit: 0,
+                exceptions: 1
+            }),
+            Some(3)
+        );
+        assert_eq!(
+            caves.count_paths(VisitRule {
+                limit: 0,
+                exceptions: 2
+            }),
+            Some(5)
+        );
+
+        assert_eq!(
+            CaveSystem::from(&parse_from_str("start-A\nA-B\nA-end")).count_paths(VisitRule::ONCE),
+            None
+        );
+    }
+
+    #[test]
+    fn test_listing_paths() {
+        let caves = CaveSystem::from(&parse_from_str(TEST_INPUT));
+        let mut paths: Vec<String> = caves
+            .paths(VisitRule::ONCE)
+            .unwrap()
+            .map(|path| caves.format_path(&path))
+            .collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec![
+                "start,A,b,A,c,A,end",
+                "start,A,b,A,end",
+                "start,A,b,end",
+                "start,A,c,A,b,A,end",
+                "start,A,c,A,b,end",
+                "start,A,c,A,end",
+                "start,A,end",
+                "start,b,A,c,A,end",
+                "start,b,A,end",
+                "start,b,end",
+            ]
+        );
+        assert_eq!(caves.paths(VisitRule::ONE_TWICE).unwrap().count(), 36);
+        assert_eq!(caves.paths(VisitRule::ONE_TWICE).unwrap().take(5).count(), 5);
+        assert!(CaveSystem::from(&parse_from_str("start-A\nA-B\nA-end"))
+            .paths(VisitRule::ONCE)
+            .is_none());
+    }
+
+    #[test]
+    fn test_dot() {
+        let dot = CaveSystem::from(&parse_from_str("start-A\nA-b\nb-end")).to_dot();
+        assert!(dot.starts_with("graph caves {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("\"A\" [shape=box, style=filled, fillcolor=lightblue];"));
+        assert!(dot.contains("\"b\" [shape=circle];"));
+        assert!(dot.contains("\"start\" [shape=doublecircle, style=filled, fillcolor=palegreen];"));
+        assert!(dot.contains("\"end\" [shape=doublecircle, style=filled, fillcolor=lightcoral];"));
+        assert_eq!(dot.matches(" -- ").count(), 3);
+        assert!(dot.contains("\"A\" -- \"b\";"));
+    }
+
+    #[test]
+    fn test_too_many_small_caves_to_pack() {
+        // A line of 70 small caves, each also joined to start and end, needs more than 128 bits of counts.
+        let mut lines: Vec<String> = (0..70).map(|i| format!("start-s{}\ns{}-end", i, i)).collect();
+        lines.extend((1..70).map(|i| format!("s{}-s{}", i - 1, i)));
+        let caves = CaveSystem::from(&parse_from_str(&lines.join("\n")));
+        assert!(matches!(caves.no_visits(2).counts, Counts::Wide(_)));
+        // Each path goes in at one cave and walks some way along the line in either direction.
+        assert_eq!(caves.count_paths(VisitRule::ONCE), Some(70 * 70));
+        for rule in [VisitRule::ONCE, VisitRule::ONE_TWICE] {
+            assert_eq!(
+                caves.count_paths(rule),
+                Some(caves.paths(rule).unwrap().count() as u128)
+            );
+        }
+    }
+
+    #[test]
+    fn test_big_generated_system() {
+        let mut rng = StdRng::seed_from_u64(12);
+        let mut total = 0;
+        let rules = [
+            VisitRule::ONCE,
+            VisitRule::ONE_TWICE,
+            VisitRule {
+                limit: 2,
+                exceptions: 1,
+            },
+        ];
+        for _ in 0..5 {
+            let caves = CaveSystem::from(&parse_from_str(&generate(&mut rng, 6, 2, 16)));
+            for rule in rules {
+                let enumerated = caves.paths(rule).unwrap().count() as u128;
+                assert_eq!(caves.count_paths(rule), Some(enumerated));
+                total += enumerated;
+            }
+        }
+        assert!(total > 0);
+    }
 }
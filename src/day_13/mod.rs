@@ -1,5 +1,10 @@
 use hashbrown::HashSet;
+use itertools::Itertools;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use std::fmt::{Display, Formatter};
 use std::fs;
+use std::iter::once;
 use std::path::Path;
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -17,7 +22,13 @@ pub fn solve_part_1() -> Result<(), ()> {
 
 pub fn solve_part_2() -> Result<(), ()> {
     let input = parse_from_file("./inputs/day13.txt");
-    part_two(input);
+    match part_two(input) {
+        Ok(letters) => println!("Solution: {}", letters),
+        Err(unknown) => {
+            println!("{}", unknown);
+            return Err(());
+        }
+    }
     Ok(())
 }
 
@@ -52,33 +63,93 @@ pub fn part_one(input: Input) -> usize {
     fold(input.0, input.1[0]).len()
 }
 
-pub fn part_two(input: Input) {
+pub fn part_two(input: Input) -> Result<String, UnknownGlyph> {
     let folds = input.1;
     let result = folds.iter().fold(input.0, |acc, new| fold(acc, *new));
-    print_dem_lettas(result);
-}
-
-fn print_dem_lettas(points: HashSet<(usize, usize)>) {
-    let (min_x, max_x) = (
-        points.iter().map(|(_, x)| *x).min().unwrap(),
-        points.iter().map(|(_, x)| *x).max().unwrap(),
-    );
-
-    let (min_y, max_y) = (
-        points.iter().map(|(y, _)| *y).min().unwrap(),
-        points.iter().map(|(y, _)| *y).max().unwrap(),
-    );
-
-    for y in min_y..=max_y {
-        println!();
-        for x in min_x..=max_x {
-            if points.contains(&(y, x)) {
-                print!("#");
-            } else {
-                print!(".");
+    read_letters(&result)
+}
+
+const GLYPH_WIDTH: usize = 4;
+const GLYPH_HEIGHT: usize = 6;
+/// Each letter is followed by a blank column.
+const GLYPH_SPACING: usize = 5;
+
+/// The letters that turn up in the puzzles, each as its rows of `#`s and `.`s.
+const FONT: [(char, [&str; GLYPH_HEIGHT]); 17] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+/// A glyph that isn't in the font, with its rows drawn in `#`s and `.`s.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct UnknownGlyph {
+    pub index: usize,
+    pub bitmap: Vec<String>,
+}
+
+impl Display for UnknownGlyph {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Glyph {} isn't a letter I know:", self.index)?;
+        write!(f, "{}", self.bitmap.join("\n"))
+    }
+}
+
+/// The rows of the glyph whose top left is at (0, `left`).
+fn glyph_at(points: &HashSet<(usize, usize)>, left: usize) -> Vec<String> {
+    (0..GLYPH_HEIGHT)
+        .map(|y| {
+            (left..left + GLYPH_WIDTH)
+                .map(|x| if points.contains(&(y, x)) { '#' } else { '.' })
+                .collect()
+        })
+        .collect()
+}
+
+/// Reads the letters along the top of the paper, the first starting in the top left corner.
+pub fn read_letters(points: &HashSet<(usize, usize)>) -> Result<String, UnknownGlyph> {
+    let width = points.iter().map(|(_, x)| *x + 1).max().unwrap_or(0);
+    (0..width)
+        .step_by(GLYPH_SPACING)
+        .enumerate()
+        .map(|(index, left)| {
+            let bitmap = glyph_at(points, left);
+            FONT.iter()
+                .find(|(_, rows)| rows.iter().zip(&bitmap).all(|(row, drawn)| row == drawn))
+                .map(|(letter, _)| *letter)
+                .ok_or(UnknownGlyph { index, bitmap })
+        })
+        .collect()
+}
+
+/// Draws the text in the font along the top of the paper, or gives back the first letter it doesn't have.
+pub fn draw_letters(text: &str) -> Result<HashSet<(usize, usize)>, char> {
+    let mut points = HashSet::new();
+    for (index, letter) in text.chars().enumerate() {
+        let (_, rows) = FONT.iter().find(|(c, _)| *c == letter).ok_or(letter)?;
+        for (y, row) in rows.iter().enumerate() {
+            for (x, cell) in row.chars().enumerate() {
+                if cell == '#' {
+                    points.insert((y, index * GLYPH_SPACING + x));
+                }
             }
         }
     }
+    Ok(points)
 }
 
 fn fold(points: HashSet<(usize, usize)>, fold: Fold) -> HashSet<(usize, usize)> {
@@ -107,9 +178,107 @@ fn fold(points: HashSet<(usize, usize)>, fold: Fold) -> HashSet<(usize, usize)>
         .collect()
 }
 
+/// Whether the point is on the side of the line that folding keeps.
+fn before_line((y, x): (usize, usize), fold: Fold) -> bool {
+    match fold {
+        Fold::X(n) => x < n,
+        Fold::Y(n) => y < n,
+    }
+}
+
+fn reflect((y, x): (usize, usize), fold: Fold) -> (usize, usize) {
+    match fold {
+        Fold::X(n) => (y, 2 * n - x),
+        Fold::Y(n) => (2 * n - y, x),
+    }
+}
+
+/// The dots that `fold` could have made this one from: itself, its reflection, or both. The dots also have to
+/// be ones that every `earlier` fold could have left behind, so each of them can be unfolded further.
+fn origins(point: (usize, usize), fold: Fold, earlier: &[Fold]) -> Vec<Vec<(usize, usize)>> {
+    if !before_line(point, fold) {
+        return vec![];
+    }
+    let reflection = reflect(point, fold);
+    [vec![point], vec![reflection], vec![point, reflection]]
+        .into_iter()
+        .filter(|dots| {
+            earlier
+                .iter()
+                .all(|earlier| dots.iter().all(|dot| before_line(*dot, *earlier)))
+        })
+        .collect()
+}
+
+/// Every set of dots that `folds` turn into `points`. Each dot has up to three origins at each fold, so
+/// there are a lot of them.
+#[allow(dead_code)]
+pub fn unfold(points: &HashSet<(usize, usize)>, folds: &[Fold]) -> Box<dyn Iterator<Item = HashSet<(usize, usize)>>> {
+    let (last, earlier_folds) = match folds.split_last() {
+        Some(split) => split,
+        None => return Box::new(once(points.clone())),
+    };
+    let options: Vec<Vec<Vec<(usize, usize)>>> = points
+        .iter()
+        .map(|point| origins(*point, *last, earlier_folds))
+        .collect();
+    let unfolded: Box<dyn Iterator<Item = HashSet<(usize, usize)>>> = if options.is_empty() {
+        Box::new(once(HashSet::new()))
+    } else {
+        Box::new(
+            options
+                .into_iter()
+                .map(|option| option.into_iter())
+                .multi_cartesian_product()
+                .map(|choices| choices.into_iter().flatten().collect()),
+        )
+    };
+    let earlier_folds = earlier_folds.to_vec();
+    Box::new(unfolded.flat_map(move |dots| unfold(&dots, &earlier_folds)))
+}
+
+/// One of the sets of dots that `folds` turn into the picture, picked at random, or `None` if the picture
+/// doesn't fit inside the folded paper.
+pub fn scramble<R: Rng>(picture: &HashSet<(usize, usize)>, folds: &[Fold], rng: &mut R) -> Option<Input> {
+    let mut points = picture.clone();
+    for (i, fold) in folds.iter().enumerate().rev() {
+        let mut unfolded = HashSet::new();
+        for point in &points {
+            unfolded.extend(origins(*point, *fold, &folds[..i]).choose(rng)?);
+        }
+        points = unfolded;
+    }
+    Some((points, folds.to_vec()))
+}
+
+/// Writes the dots and folds out the way the puzzle input has them.
+pub fn write_input((points, folds): &Input) -> String {
+    let mut input: String = points.iter().sorted().map(|(y, x)| format!("{},{}\n", x, y)).collect();
+    input += "\n";
+    for fold in folds {
+        input += &match fold {
+            Fold::X(n) => format!("fold along x={}\n", n),
+            Fold::Y(n) => format!("fold along y={}\n", n),
+        };
+    }
+    input
+}
+
+/// Prints a new puzzle input that folds up into the text, using the folds from the real one.
+pub fn print_puzzle(text: &str) -> Result<(), ()> {
+    let (_, folds) = parse_from_file("./inputs/day13.txt");
+    let picture = draw_letters(text).map_err(|letter| println!("You wot? There's no {} in the font", letter))?;
+    let input = scramble(&picture, &folds, &mut rand::thread_rng())
+        .ok_or_else(|| println!("You wot? {} doesn't fit on the folded paper", text))?;
+    print!("{}", write_input(&input));
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
 
     const TEST_INPUT: &str = include_str!("../../test_inputs/day13.txt");
 
@@ -128,8 +297,105 @@ mod tests {
     }
 
     #[test]
-    #[ignore]
     fn test_part_two() {
-        unimplemented!();
+        let input = parse_from_str(TEST_INPUT);
+        let unknown = part_two(input).unwrap_err();
+        assert_eq!(unknown.index, 0);
+        assert_eq!(unknown.bitmap, vec!["####", "#...", "#...", "#...", "####", "...."]);
+        assert_eq!(
+            unknown.to_string(),
+            "Glyph 0 isn't a letter I know:\n####\n#...\n#...\n#...\n####\n...."
+        );
+    }
+
+    #[test]
+    fn test_read_letters() {
+        let alphabet: String = FONT.iter().map(|(letter, _)| *letter).collect();
+        assert_eq!(read_letters(&draw_letters(&alphabet).unwrap()), Ok(alphabet));
+        assert_eq!(read_letters(&draw_letters("FIZZ").unwrap()), Ok("FIZZ".to_string()));
+        assert_eq!(read_letters(&HashSet::new()), Ok(String::new()));
+
+        let mut points = draw_letters("HELLO").unwrap();
+        points.remove(&(5, 10));
+        let unknown = read_letters(&points).unwrap_err();
+        assert_eq!(unknown.index, 2);
+        assert_eq!(unknown.bitmap, vec!["#...", "#...", "#...", "#...", "#...", ".###"]);
+    }
+
+    #[test]
+    fn test_unfold() {
+        let points: HashSet<(usize, usize)> = [(0, 0), (0, 1)].into_iter().collect();
+        let unfolded: Vec<HashSet<(usize, usize)>> = unfold(&points, &[Fold::X(2)]).collect();
+        assert_eq!(unfolded.len(), 9);
+        for dots in &unfolded {
+            assert_eq!(fold(dots.clone(), Fold::X(2)), points);
+        }
+        assert_eq!(
+            unfolded
+                .iter()
+                .unique_by(|dots| dots.iter().sorted().collect_vec())
+                .count(),
+            9
+        );
+
+        let folds = [Fold::X(4), Fold::Y(2)];
+        let unfolded: Vec<HashSet<(usize, usize)>> = unfold(&points, &folds).collect();
+        assert_eq!(unfolded.len(), 225);
+        for dots in unfolded {
+            assert_eq!(folds.iter().fold(dots, |dots, next| fold(dots, *next)), points);
+        }
+
+        assert_eq!(unfold(&points, &[Fold::X(1)]).count(), 0);
+        assert_eq!(unfold(&HashSet::new(), &folds).count(), 1);
+    }
+
+    #[test]
+    fn test_unfold_the_example() {
+        let (points, folds) = parse_from_str(TEST_INPUT);
+        let folded = folds.iter().fold(points.clone(), |dots, next| fold(dots, *next));
+        let corner: HashSet<(usize, usize)> = points.iter().filter(|(y, _)| *y < 3 || *y > 11).copied().collect();
+        let first = fold(corner.clone(), folds[0]);
+        assert!(unfold(&first, &folds[..1]).any(|dots| dots == corner));
+        assert!(unfold(&folded, &folds)
+            .take(1000)
+            .all(|dots| { folds.iter().fold(dots, |dots, next| fold(dots, *next)) == folded }));
+    }
+
+    #[test]
+    fn test_scramble() {
+        let mut rng = rand::thread_rng();
+        let folds = [
+            Fold::X(655),
+            Fold::Y(447),
+            Fold::X(327),
+            Fold::Y(223),
+            Fold::X(163),
+            Fold::Y(111),
+            Fold::X(81),
+            Fold::Y(55),
+            Fold::X(40),
+            Fold::Y(27),
+            Fold::Y(13),
+            Fold::Y(6),
+        ];
+        let picture = draw_letters("ABCEFGHI").unwrap();
+        for _ in 0..10 {
+            let input = scramble(&picture, &folds, &mut rng).unwrap();
+            assert_eq!(parse_from_str(&write_input(&input)), input);
+            assert_eq!(part_two(input), Ok("ABCEFGHI".to_string()));
+        }
+        assert_eq!(scramble(&draw_letters("ABCEFGHIJ").unwrap(), &folds, &mut rng), None);
+    }
+
+    #[test]
+    fn test_scramble_same_axis_twice() {
+        // Unfolding y=3 could reach y=6, which the fold along y=4 would never have left.
+        let mut rng = StdRng::seed_from_u64(13);
+        let folds = [Fold::Y(4), Fold::X(5), Fold::Y(3)];
+        let picture: HashSet<(usize, usize)> = [(0, 0), (1, 2), (2, 4)].into_iter().collect();
+        for _ in 0..50 {
+            let (dots, _) = scramble(&picture, &folds, &mut rng).unwrap();
+            assert_eq!(folds.iter().fold(dots, |dots, next| fold(dots, *next)), picture);
+        }
     }
 }
@@ -1,5 +1,7 @@
-use counter::Counter;
+use crate::helpers::matrix::Matrix;
 use itertools::Itertools;
+use num::traits::Zero;
+use num::{BigUint, ToPrimitive};
 use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
@@ -38,66 +40,98 @@ fn parse_from_str(input: &str) -> Input {
 }
 
 pub fn part_one(input: Input) -> usize {
-    get_answer_after_all_dem_iterations(input, 10)
+    Polymer::from(&input).spread_after(10).to_usize().unwrap()
 }
 
 pub fn part_two(input: Input) -> usize {
-    get_answer_after_all_dem_iterations(input, 40)
+    Polymer::from(&input).spread_after(40).to_usize().unwrap()
 }
 
-fn get_answer_after_all_dem_iterations((initial_value, inbetweeners): Input, iterations: usize) -> usize {
-    let initial_count: Counter<char> = initial_value.chars().collect();
-    let initial_count: BTreeMap<char, usize> = initial_count.into_iter().map(|(&c, &count)| (c, count)).collect();
-    let mut cache = BTreeMap::new();
-    let resulting: BTreeMap<char, usize> = initial_value
-        .chars()
-        .tuple_windows()
-        .map(|(left, right)| get_between_count((left, right), iterations, &inbetweeners, &mut cache))
-        .fold(initial_count, combine_dem_counts);
-
-    let mut resulting: Vec<(char, usize)> = resulting.into_iter().collect();
-    resulting.sort_by(|a, b| a.1.cmp(&b.1));
-
-    resulting.last().unwrap().1 - resulting.first().unwrap().1
+/// A polymer kept as how many times each pair of elements appears next to each other. Every step turns each
+/// pair with a rule into two, which is linear, so a step is a matrix and any number of them is a power of it.
+#[derive(Debug, Clone)]
+pub struct Polymer {
+    elements: Vec<char>,
+    pair_counts: Vec<BigUint>,
+    last: usize,
+    step_matrix: Matrix<BigUint>,
 }
 
-fn combine_dem_counts(mut lhs: BTreeMap<char, usize>, rhs: BTreeMap<char, usize>) -> BTreeMap<char, usize> {
-    for (char, count) in rhs.into_iter() {
-        *lhs.entry(char).or_insert(0) += count;
+impl From<&Input> for Polymer {
+    fn from((template, rules): &Input) -> Self {
+        let mut elements: Vec<char> = template
+            .chars()
+            .chain(rules.iter().flat_map(|(&(left, right), &inner)| [left, right, inner]))
+            .collect();
+        elements.sort_unstable();
+        elements.dedup();
+        let index = |element: char| elements.binary_search(&element).unwrap();
+        let size = elements.len();
+        let pair = |left: char, right: char| index(left) * size + index(right);
+
+        let mut pair_counts = vec![BigUint::zero(); size * size];
+        for (left, right) in template.chars().tuple_windows() {
+            pair_counts[pair(left, right)] += 1u32;
+        }
+        let mut step_matrix = Matrix::zero(size * size);
+        for &left in &elements {
+            for &right in &elements {
+                let from = pair(left, right);
+                match rules.get(&(left, right)) {
+                    Some(&inner) => {
+                        step_matrix.values[pair(left, inner)][from] += 1u32;
+                        step_matrix.values[pair(inner, right)][from] += 1u32;
+                    }
+                    None => step_matrix.values[from][from] += 1u32,
+                }
+            }
+        }
+        let last = index(template.chars().last().expect("You wot? The template's empty"));
+        Polymer {
+            elements,
+            pair_counts,
+            last,
+            step_matrix,
+        }
     }
-    lhs
 }
 
-type ArgTuple = ((char, char), usize);
-type CachedResultType = BTreeMap<char, usize>;
-type Cache = BTreeMap<ArgTuple, CachedResultType>;
-fn get_between_count(
-    (left, right): (char, char),
-    iterations_remaining: usize,
-    inbetweeners: &BTreeMap<(char, char), char>,
-    cache: &mut Cache,
-) -> BTreeMap<char, usize> {
-    if iterations_remaining == 0 {
-        return BTreeMap::new();
+impl Polymer {
+    /// How many of each pair there are after `steps`, indexed by left element then right.
+    pub fn pair_counts_after(&self, steps: u64) -> Vec<BigUint> {
+        self.step_matrix.pow(steps).apply(&self.pair_counts)
+    }
+
+    /// One step on from some pair counts.
+    #[allow(dead_code)]
+    pub fn step(&self, pair_counts: &[BigUint]) -> Vec<BigUint> {
+        self.step_matrix.apply(pair_counts)
+    }
+
+    /// How many of each element there are for some pair counts. Every element is the left of a pair except
+    /// the template's last, which never moves.
+    pub fn histogram(&self, pair_counts: &[BigUint]) -> BTreeMap<char, BigUint> {
+        let mut histogram: BTreeMap<char, BigUint> = BTreeMap::new();
+        for (pair, count) in pair_counts.iter().enumerate() {
+            let left = self.elements[pair / self.elements.len()];
+            *histogram.entry(left).or_insert_with(BigUint::zero) += count;
+        }
+        *histogram.entry(self.elements[self.last]).or_insert_with(BigUint::zero) += 1u32;
+        histogram.retain(|_, count| !count.is_zero());
+        histogram
     }
 
-    if let Some(cached_val) = cache.get(&((left, right), iterations_remaining)) {
-        return cached_val.clone();
+    pub fn histogram_after(&self, steps: u64) -> BTreeMap<char, BigUint> {
+        self.histogram(&self.pair_counts_after(steps))
     }
 
-    let inbetweener = *inbetweeners.get(&(left, right)).unwrap();
-    let mut result = BTreeMap::new();
-    result.insert(inbetweener, 1);
-    let result = combine_dem_counts(
-        result,
-        get_between_count((left, inbetweener), iterations_remaining - 1, inbetweeners, cache),
-    );
-    let result = combine_dem_counts(
-        result,
-        get_between_count((inbetweener, right), iterations_remaining - 1, inbetweeners, cache),
-    );
-    cache.insert(((left, right), iterations_remaining), result.clone());
-    result
+    /// The most common element's count minus the least common's.
+    pub fn spread_after(&self, steps: u64) -> BigUint {
+        let histogram = self.histogram_after(steps);
+        let most = histogram.values().max().unwrap();
+        let least = histogram.values().min().unwrap();
+        most - least
+    }
 }
 
 #[cfg(test)]
@@ -106,15 +140,6 @@ mod tests {
 
     const TEST_INPUT: &str = include_str!("../../test_inputs/day14.txt");
 
-    #[test]
-    #[ignore]
-    fn test_combining() {
-        let left: BTreeMap<char, usize> = [('a', 3), ('b', 1)].into();
-        let right: BTreeMap<char, usize> = [('b', 4), ('c', 2)].into();
-        let result: BTreeMap<char, usize> = [('a', 3), ('b', 5), ('c', 2)].into();
-        assert_eq!(combine_dem_counts(left, right), result)
-    }
-
     #[test]
     fn test_part_one() {
         let input = parse_from_str(TEST_INPUT);
@@ -126,4 +151,52 @@ mod tests {
         let input = parse_from_str(TEST_INPUT);
         assert_eq!(part_two(input), 2188189693529)
     }
+
+    fn histogram(counts: &[(char, u64)]) -> BTreeMap<char, BigUint> {
+        counts.iter().map(|&(c, count)| (c, BigUint::from(count))).collect()
+    }
+
+    #[test]
+    fn test_histograms() {
+        let polymer = Polymer::from(&parse_from_str(TEST_INPUT));
+        assert_eq!(polymer.histogram_after(0), histogram(&[('B', 1), ('C', 1), ('N', 2)]));
+        assert_eq!(
+            polymer.histogram_after(1),
+            histogram(&[('B', 2), ('C', 2), ('H', 1), ('N', 2)])
+        );
+        assert_eq!(
+            polymer.histogram_after(10),
+            histogram(&[('B', 1749), ('C', 298), ('H', 161), ('N', 865)])
+        );
+        assert_eq!(
+            polymer.histogram_after(2),
+            histogram(&[('B', 6), ('C', 4), ('H', 1), ('N', 2)])
+        );
+        assert_eq!(
+            polymer.histogram_after(3),
+            histogram(&[('B', 11), ('C', 5), ('H', 4), ('N', 5)])
+        );
+        assert_eq!(polymer.spread_after(10), BigUint::from(1588u32));
+        assert_eq!(polymer.spread_after(40), BigUint::from(2188189693529u64));
+    }
+
+    #[test]
+    fn test_many_steps() {
+        let polymer = Polymer::from(&parse_from_str(TEST_INPUT));
+        let mut pair_counts = polymer.pair_counts_after(0);
+        for _ in 0..200 {
+            pair_counts = polymer.step(&pair_counts);
+        }
+        assert_eq!(polymer.pair_counts_after(200), pair_counts);
+
+        // Each step nearly doubles the length, so NNCB grows to 3 * 2^steps + 1 elements.
+        let length: BigUint = polymer.histogram_after(200).values().sum();
+        assert_eq!(length, BigUint::from(3u32) * (BigUint::from(1u32) << 200usize) + 1u32);
+    }
+
+    #[test]
+    fn test_pairs_without_rules() {
+        let polymer = Polymer::from(&parse_from_str("ABA\n\nAB -> A"));
+        assert_eq!(polymer.histogram_after(3), histogram(&[('A', 5), ('B', 1)]));
+    }
 }
@@ -1,4 +1,8 @@
 use hashbrown::HashMap;
+use num::rational::BigRational;
+use num::traits::{One, Pow, Zero};
+use num::{BigInt, BigUint};
+use std::fmt::{Display, Formatter};
 
 #[derive(Debug, Hash, Eq, PartialEq, Clone)]
 pub struct Game {
@@ -41,123 +45,293 @@ pub fn solve_part_2() -> Result<(), ()> {
     Ok(())
 }
 
-fn iterate_game(game: Game) -> (usize, usize) {
-    let mut rolls = 0;
-    let mut dice_iter = (1usize..=100usize).cycle();
-    let Game {
-        mut player_one,
-        mut player_two,
-        mut player_one_score,
-        mut player_two_score,
-        target,
-        ..
-    } = game;
-
-    while player_one_score < target && player_two_score < target {
-        let (current_player, current_score) = if rolls % 2 == 0 {
-            (&mut player_one, &mut player_one_score)
-        } else {
-            (&mut player_two, &mut player_two_score)
-        };
-        let new_roll: usize = dice_iter.by_ref().take(3).sum();
-        rolls += 1;
-        *current_player += new_roll;
-        let scored = if *current_player % 10 == 0 {
-            10
-        } else {
-            *current_player % 10
-        };
-        *current_score += scored;
-        if *current_player > 10 {
-            *current_player %= 10
-        };
-    }
-
-    let losing_score = if player_one_score >= target {
-        player_two_score
-    } else {
-        player_one_score
+pub fn part_one(input: Input) -> usize {
+    let rules = Rules {
+        target: input.target,
+        ..Rules::PRACTICE
     };
-    (rolls * 3, losing_score)
+    let outcome = rules.play_practice(&State::from(&input));
+    outcome.rolls * outcome.losing_score()
 }
 
-fn iterate_game_once(mut game: Game, new_rolls: (usize, usize, usize)) -> Game {
-    let (current_player, current_score) = if game.player_turn == 0 {
-        (&mut game.player_one, &mut game.player_one_score)
-    } else {
-        (&mut game.player_two, &mut game.player_two_score)
-    };
-    *current_player += new_rolls.0 + new_rolls.1 + new_rolls.2;
-    let scored = if *current_player % 10 == 0 {
-        10
-    } else {
-        *current_player % 10
-    };
-    *current_score += scored;
-    if *current_player > 10 {
-        *current_player %= 10
-    };
-    game.player_turn += 1;
-    game.player_turn %= 2;
-    game
+/// How the game is played: pawns go round a board of `spaces` numbered from 1, each turn a player rolls a
+/// die with `faces` sides `rolls` times and moves on by the total, and the first to reach `target` wins.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Rules {
+    pub spaces: usize,
+    pub faces: usize,
+    pub rolls: usize,
+    pub target: usize,
 }
 
-pub fn part_one(input: Input) -> usize {
-    let (rolls, losing_score) = iterate_game(input);
-    rolls * losing_score
+/// Where each player is and what they've scored, and whose turn it is.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct State {
+    pub positions: Vec<usize>,
+    pub scores: Vec<usize>,
+    pub turn: usize,
 }
 
-type CountWinningArg = (Game, (usize, usize, usize));
-type CountWinningResult = (usize, usize);
-type Cache = HashMap<CountWinningArg, CountWinningResult>;
-fn count_winning_outcomes(
-    game: Game,
-    rolls: (usize, usize, usize),
-    cache: &mut Cache,
-) -> (usize, usize) {
-    if cache.get(&(game.clone(), rolls)).is_some() {
-        return *cache.get(&(game, rolls)).unwrap();
+impl State {
+    pub fn new(starts: &[usize]) -> Self {
+        State {
+            positions: starts.to_vec(),
+            scores: vec![0; starts.len()],
+            turn: 0,
+        }
     }
+}
+
+impl From<&Game> for State {
+    fn from(game: &Game) -> Self {
+        State {
+            positions: vec![game.player_one, game.player_two],
+            scores: vec![game.player_one_score, game.player_two_score],
+            turn: game.player_turn,
+        }
+    }
+}
+
+/// How a game with the deterministic die went.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Outcome {
+    pub rolls: usize,
+    pub scores: Vec<usize>,
+    pub winner: usize,
+}
+
+impl Outcome {
+    pub fn losing_score(&self) -> usize {
+        losing_score(&self.scores, self.winner)
+    }
+}
+
+/// The lowest score of anyone who didn't win.
+fn losing_score(scores: &[usize], winner: usize) -> usize {
+    scores
+        .iter()
+        .enumerate()
+        .filter(|(player, _)| *player != winner)
+        .map(|(_, score)| *score)
+        .min()
+        .unwrap_or(0)
+}
 
-    let next_game = iterate_game_once(game.clone(), rolls);
+/// The odds of a game with the Dirac die, where every roll is equally likely.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Analysis {
+    pub win_probabilities: Vec<BigRational>,
+    /// The chance of the game ending on each turn, starting from no turns at all.
+    pub lengths: Vec<BigRational>,
+    pub expected_losing_score: BigRational,
+}
 
-    // Determine if there is a winner already (this is the base case)
-    if next_game.player_one_score >= next_game.target {
-        cache.insert((game, rolls), (1, 0));
-        return (1, 0);
+impl Analysis {
+    pub fn expected_length(&self) -> BigRational {
+        self.lengths
+            .iter()
+            .enumerate()
+            .fold(BigRational::zero(), |sum, (turns, chance)| {
+                sum + chance * BigRational::from_integer(BigInt::from(turns))
+            })
     }
+}
 
-    if next_game.player_two_score >= next_game.target {
-        cache.insert((game, rolls), (0, 1));
-        return (0, 1);
+impl Display for Analysis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (player, chance) in self.win_probabilities.iter().enumerate() {
+            writeln!(f, "Player {} wins {} of the time", player + 1, decimal(chance))?;
+        }
+        writeln!(f, "Games last {} turns on average", decimal(&self.expected_length()))?;
+        for (turns, chance) in self.lengths.iter().enumerate().filter(|(_, chance)| !chance.is_zero()) {
+            writeln!(f, "{:>4} turns: {}", turns, decimal(chance))?;
+        }
+        write!(
+            f,
+            "The loser scores {} on average",
+            decimal(&self.expected_losing_score)
+        )
     }
+}
 
-    let results: Vec<_> = base_three_tuples()
-        .map(|rolls| count_winning_outcomes(next_game.clone(), rolls, cache))
-        .collect();
-    let player_one_wins = results.iter().map(|(one_wins, _)| one_wins).sum();
-    let player_two_wins = results.iter().map(|(_, two_wins)| two_wins).sum();
-    cache.insert((game, rolls), (player_one_wins, player_two_wins));
-    (player_one_wins, player_two_wins)
+/// Six decimal places are plenty to read a probability by.
+fn decimal(value: &BigRational) -> String {
+    let millionths = (value * BigRational::from_integer(BigInt::from(1_000_000)))
+        .round()
+        .to_integer();
+    let (whole, fraction) = (&millionths / 1_000_000, &millionths % 1_000_000);
+    format!("{}.{:06}", whole, fraction)
 }
 
-pub fn part_two(game: Input) -> usize {
-    let mut cache: Cache = HashMap::new();
+type WinCache = HashMap<State, Vec<u128>>;
+
+impl Rules {
+    pub const PRACTICE: Rules = Rules {
+        spaces: 10,
+        faces: 100,
+        rolls: 3,
+        target: 1000,
+    };
+    pub const DIRAC: Rules = Rules {
+        spaces: 10,
+        faces: 3,
+        rolls: 3,
+        target: 21,
+    };
+
+    /// Each total a turn's rolls can add up to, with how many ways of rolling it there are.
+    pub fn roll_sums(&self) -> Vec<(usize, u128)> {
+        if self.faces == 0 {
+            panic!("You wot? A die needs at least one face");
+        }
+        let mut ways = vec![1u128];
+        for _ in 0..self.rolls {
+            let mut next = vec![0; ways.len() + self.faces];
+            for (total, count) in ways.iter().enumerate() {
+                for face in 1..=self.faces {
+                    next[total + face] += count;
+                }
+            }
+            ways = next;
+        }
+        ways.into_iter().enumerate().filter(|(_, count)| *count > 0).collect()
+    }
+
+    /// Moves the player whose turn it is on by `total`, returning whether they've won.
+    fn take_turn(&self, state: &mut State, total: usize) -> bool {
+        let player = state.turn;
+        state.positions[player] = (state.positions[player] - 1 + total) % self.spaces + 1;
+        state.scores[player] += state.positions[player];
+        state.turn = (player + 1) % state.positions.len();
+        state.scores[player] >= self.target
+    }
+
+    /// Plays with a die that rolls 1, 2, 3 and so on up to its number of faces, then starts again.
+    pub fn play_practice(&self, start: &State) -> Outcome {
+        let mut state = start.clone();
+        let mut rolls = 0;
+        loop {
+            let total = (rolls..rolls + self.rolls).map(|roll| roll % self.faces + 1).sum();
+            rolls += self.rolls;
+            let player = state.turn;
+            if self.take_turn(&mut state, total) {
+                return Outcome {
+                    rolls,
+                    scores: state.scores,
+                    winner: player,
+                };
+            }
+        }
+    }
+
+    /// How many universes each player wins in with the Dirac die, which splits the universe on every roll.
+    pub fn count_wins(&self, start: &State) -> Vec<u128> {
+        let roll_sums = self.roll_sums();
+        self.count_wins_from(start, &roll_sums, &mut HashMap::new())
+    }
+
+    /// Works out the odds by playing every turn for all the games still going at once. Each game's weight
+    /// is how many ways it could have been rolled, so its chance is that over all the rolls there could
+    /// have been.
+    pub fn analyse(&self, start: &State) -> Analysis {
+        let players = start.positions.len();
+        let roll_sums: Vec<(usize, BigUint)> = self
+            .roll_sums()
+            .into_iter()
+            .map(|(total, ways)| (total, BigUint::from(ways)))
+            .collect();
+        let outcomes_per_turn = BigUint::from(self.faces).pow(self.rolls as u32);
+        let chance = |weight: BigUint, outcomes: &BigUint| {
+            BigRational::new(BigInt::from(weight), BigInt::from(outcomes.clone()))
+        };
+
+        let mut analysis = Analysis {
+            win_probabilities: vec![BigRational::zero(); players],
+            lengths: vec![BigRational::zero()],
+            expected_losing_score: BigRational::zero(),
+        };
+        let mut games: HashMap<State, BigUint> = HashMap::new();
+        games.insert(start.clone(), BigUint::one());
+        let mut outcomes = BigUint::one();
+        while !games.is_empty() {
+            outcomes *= &outcomes_per_turn;
+            let mut wins = vec![BigUint::zero(); players];
+            let mut losing_scores = BigUint::zero();
+            let mut next_games: HashMap<State, BigUint> = HashMap::new();
+            for (game, weight) in games {
+                for (total, ways) in &roll_sums {
+                    let mut next = game.clone();
+                    let weight = &weight * ways;
+                    if self.take_turn(&mut next, *total) {
+                        losing_scores += &weight * losing_score(&next.scores, game.turn);
+                        wins[game.turn] += weight;
+                    } else {
+                        *next_games.entry(next).or_insert_with(BigUint::zero) += weight;
+                    }
+                }
+            }
+            let ended: BigUint = wins.iter().sum();
+            analysis.lengths.push(chance(ended, &outcomes));
+            for (player, weight) in wins.into_iter().enumerate() {
+                analysis.win_probabilities[player] += chance(weight, &outcomes);
+            }
+            analysis.expected_losing_score += chance(losing_scores, &outcomes);
+            games = next_games;
+        }
+        analysis
+    }
+
+    fn count_wins_from(&self, state: &State, roll_sums: &[(usize, u128)], cache: &mut WinCache) -> Vec<u128> {
+        if let Some(wins) = cache.get(state) {
+            return wins.clone();
+        }
+        let mut wins = vec![0; state.positions.len()];
+        for &(total, ways) in roll_sums {
+            let mut next = state.clone();
+            if self.take_turn(&mut next, total) {
+                wins[state.turn] += ways;
+            } else {
+                for (player, count) in self.count_wins_from(&next, roll_sums, cache).into_iter().enumerate() {
+                    wins[player] += ways * count;
+                }
+            }
+        }
+        cache.insert(state.clone(), wins.clone());
+        wins
+    }
+}
 
-    let results: Vec<_> = base_three_tuples()
-        .map(|rolls| count_winning_outcomes(game.clone(), rolls, &mut cache))
-        .collect();
-    let player_one_wins = results.iter().map(|(one_wins, _)| one_wins).sum();
-    let player_two_wins = results.iter().map(|(_, two_wins)| two_wins).sum();
-    if player_one_wins > player_two_wins {
-        player_one_wins
-    } else {
-        player_two_wins
+/// Starting spaces like `4,8`, one for each player.
+fn parse_starts(input: &str, rules: &Rules) -> Result<Vec<usize>, String> {
+    let starts = input
+        .split(',')
+        .map(|start| match start.trim().parse() {
+            Ok(start) if (1..=rules.spaces).contains(&start) => Ok(start),
+            _ => Err(format!("{} isn't a space from 1 to {}", start, rules.spaces)),
+        })
+        .collect::<Result<Vec<usize>, String>>()?;
+    if starts.len() < 2 {
+        return Err(format!("A game needs at least 2 players, not {}", starts.len()));
     }
+    Ok(starts)
 }
 
-fn base_three_tuples() -> impl Iterator<Item = (usize, usize, usize)> {
-    (0usize..((3 * 3 * 3) as usize)).map(|i| ((i / (3 * 3)) % 3 + 1, (i / 3) % 3 + 1, i % 3 + 1))
+/// Prints the odds of the Dirac game from the given starts, or from the puzzle's if there aren't any.
+pub fn print_stats(starts: Option<String>) -> Result<(), ()> {
+    let starts = match starts {
+        Some(starts) => parse_starts(&starts, &Rules::DIRAC).map_err(|error| println!("{}", error))?,
+        None => vec![6, 8],
+    };
+    println!("{}", Rules::DIRAC.analyse(&State::new(&starts)));
+    Ok(())
+}
+
+pub fn part_two(game: Input) -> usize {
+    let rules = Rules {
+        target: game.target,
+        ..Rules::DIRAC
+    };
+    *rules.count_wins(&State::from(&game)).iter().max().unwrap() as usize
 }
 
 #[cfg(test)]
@@ -190,10 +364,177 @@ mod tests {
     }
 
     #[test]
-    #[ignore]
-    fn test_combos() {
-        let x: Vec<_> = base_three_tuples().collect();
-        println!("{:?}", x);
-        assert_eq!(x.len(), 27)
+    fn test_roll_sums() {
+        assert_eq!(
+            Rules::DIRAC.roll_sums(),
+            vec![(3, 1), (4, 3), (5, 6), (6, 7), (7, 6), (8, 3), (9, 1)]
+        );
+        let rules = Rules {
+            faces: 6,
+            rolls: 2,
+            ..Rules::DIRAC
+        };
+        assert_eq!(rules.roll_sums().iter().map(|(_, ways)| ways).sum::<u128>(), 36);
+        assert_eq!(rules.roll_sums()[5], (7, 6));
+    }
+
+    #[test]
+    fn test_known_answers() {
+        let outcome = Rules::PRACTICE.play_practice(&State::new(&[4, 8]));
+        assert_eq!((outcome.rolls, outcome.losing_score()), (993, 745));
+        assert_eq!(outcome.rolls * outcome.losing_score(), 739785);
+        assert_eq!(
+            Rules::DIRAC.count_wins(&State::new(&[4, 8])),
+            vec![444356092776315, 341960390180808]
+        );
+
+        let one_roll = Rules {
+            rolls: 1,
+            target: 12,
+            ..Rules::DIRAC
+        };
+        for starts in [[4, 8], [6, 8], [1, 10], [7, 3]] {
+            let start = State::new(&starts);
+            assert_eq!(one_roll.count_wins(&start), brute_force_wins(&one_roll, &start));
+        }
+    }
+
+    #[test]
+    fn test_parse_starts() {
+        assert_eq!(parse_starts("4,8", &Rules::DIRAC), Ok(vec![4, 8]));
+        assert_eq!(parse_starts("1, 10, 3", &Rules::DIRAC), Ok(vec![1, 10, 3]));
+        assert!(parse_starts("4", &Rules::DIRAC).is_err());
+        assert!(parse_starts("0,8", &Rules::DIRAC).is_err());
+        assert!(parse_starts("4,11", &Rules::DIRAC).is_err());
+        assert!(parse_starts("4,x", &Rules::DIRAC).is_err());
+    }
+
+    #[test]
+    fn test_other_games() {
+        let outcome = Rules::PRACTICE.play_practice(&State::new(&[4, 8]));
+        assert_eq!(outcome.winner, 0);
+        assert_eq!(outcome.scores, vec![1000, 745]);
+
+        let three_players = Rules {
+            target: 10,
+            ..Rules::DIRAC
+        };
+        let wins = three_players.count_wins(&State::new(&[1, 5, 9]));
+        assert_eq!(wins.len(), 3);
+        assert!(wins.iter().all(|wins| *wins > 0));
+
+        // With a one-faced die every universe is the same game.
+        let single = Rules {
+            faces: 1,
+            target: 100,
+            ..Rules::PRACTICE
+        };
+        let outcome = single.play_practice(&State::new(&[4, 8, 2]));
+        let mut wins = vec![0; 3];
+        wins[outcome.winner] = 1;
+        assert_eq!(single.count_wins(&State::new(&[4, 8, 2])), wins);
+
+        let small_board = Rules {
+            spaces: 4,
+            faces: 2,
+            rolls: 1,
+            target: 5,
+        };
+        assert_eq!(
+            small_board.count_wins(&State::new(&[1, 1])),
+            brute_force_wins(&small_board, &State::new(&[1, 1]))
+        );
+    }
+
+    /// Splits the universe on each roll, which only matches a turn when there's one roll per turn.
+    fn brute_force_wins(rules: &Rules, state: &State) -> Vec<u128> {
+        let mut wins = vec![0; state.positions.len()];
+        for face in 1..=rules.faces {
+            let mut next = state.clone();
+            if rules.take_turn(&mut next, face) {
+                wins[state.turn] += 1;
+            } else {
+                for (player, count) in brute_force_wins(rules, &next).into_iter().enumerate() {
+                    wins[player] += count;
+                }
+            }
+        }
+        wins
+    }
+
+    fn ratio(numerator: i64, denominator: i64) -> BigRational {
+        BigRational::new(BigInt::from(numerator), BigInt::from(denominator))
+    }
+
+    /// The chance of each player winning, found by trying every roll one at a time.
+    fn brute_force_chances(rules: &Rules, state: &State) -> Vec<BigRational> {
+        let mut chances = vec![BigRational::zero(); state.positions.len()];
+        let face = ratio(1, rules.faces as i64);
+        for roll in 1..=rules.faces {
+            let mut next = state.clone();
+            if rules.take_turn(&mut next, roll) {
+                chances[state.turn] += &face;
+            } else {
+                for (player, chance) in brute_force_chances(rules, &next).into_iter().enumerate() {
+                    chances[player] += &face * chance;
+                }
+            }
+        }
+        chances
+    }
+
+    #[test]
+    fn test_analysis() {
+        let small_board = Rules {
+            spaces: 4,
+            faces: 2,
+            rolls: 1,
+            target: 6,
+        };
+        let start = State::new(&[1, 3]);
+        let analysis = small_board.analyse(&start);
+        assert_eq!(analysis.win_probabilities, brute_force_chances(&small_board, &start));
+        assert_eq!(
+            analysis.win_probabilities.iter().sum::<BigRational>(),
+            BigRational::one()
+        );
+        assert_eq!(analysis.lengths.iter().sum::<BigRational>(), BigRational::one());
+
+        let analysis = Rules::DIRAC.analyse(&State::new(&[4, 8]));
+        assert_eq!(
+            analysis.win_probabilities.iter().sum::<BigRational>(),
+            BigRational::one()
+        );
+        assert_eq!(analysis.lengths.iter().sum::<BigRational>(), BigRational::one());
+        assert!(analysis.win_probabilities[0] > ratio(1, 2));
+        // Nobody can reach 21 in fewer than three turns of their own.
+        assert!(analysis.lengths[..5].iter().all(|chance| chance.is_zero()));
+        assert!(!analysis.lengths[5].is_zero());
+        assert!(analysis.expected_losing_score < BigRational::from_integer(BigInt::from(21)));
+    }
+
+    #[test]
+    fn test_certain_games() {
+        let single = Rules {
+            faces: 1,
+            target: 100,
+            ..Rules::PRACTICE
+        };
+        let start = State::new(&[4, 8, 2]);
+        let outcome = single.play_practice(&start);
+        let analysis = single.analyse(&start);
+        let mut chances = vec![BigRational::zero(); 3];
+        chances[outcome.winner] = BigRational::one();
+        assert_eq!(analysis.win_probabilities, chances);
+        assert_eq!(
+            analysis.expected_length(),
+            BigRational::from_integer(BigInt::from(outcome.rolls / single.rolls))
+        );
+        assert_eq!(
+            analysis.expected_losing_score,
+            BigRational::from_integer(BigInt::from(outcome.losing_score()))
+        );
+        assert_eq!(decimal(&ratio(2, 3)), "0.666667");
+        assert_eq!(decimal(&ratio(41, 4)), "10.250000");
     }
 }
@@ -13,7 +13,7 @@ pub enum Action {
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
-struct Cuboid {
+pub struct Cuboid {
     x_range: (i64, i64),
     y_range: (i64, i64),
     z_range: (i64, i64),
@@ -21,13 +21,40 @@ struct Cuboid {
 
 #[allow(dead_code)]
 impl Cuboid {
-    fn new(x_range: (i64, i64), y_range: (i64, i64), z_range: (i64, i64)) -> Cuboid {
+    pub fn new(x_range: (i64, i64), y_range: (i64, i64), z_range: (i64, i64)) -> Cuboid {
         Cuboid {
             x_range,
             y_range,
             z_range,
         }
     }
+
+    fn volume(&self) -> usize {
+        usize::try_from(
+            (self.x_range.1 - self.x_range.0 + 1)
+                * (self.y_range.1 - self.y_range.0 + 1)
+                * (self.z_range.1 - self.z_range.0 + 1),
+        )
+        .unwrap()
+    }
+
+    fn contains(&self, (x, y, z): (i64, i64, i64)) -> bool {
+        (self.x_range.0..=self.x_range.1).contains(&x)
+            && (self.y_range.0..=self.y_range.1).contains(&y)
+            && (self.z_range.0..=self.z_range.1).contains(&z)
+    }
+
+    fn intersection(&self, other: &Cuboid) -> Option<Cuboid> {
+        if !check_cubes_overlap(*self, *other) {
+            return None;
+        }
+        let overlap = get_overlap(self, other);
+        Some(Cuboid {
+            x_range: overlap.x.overlap?,
+            y_range: overlap.y.overlap?,
+            z_range: overlap.z.overlap?,
+        })
+    }
 }
 
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
@@ -144,33 +171,114 @@ fn efficient_part_one(input: Input) -> usize {
 }
 
 pub fn part_two(input: Input) -> usize {
-    let mut cuboids: HashSet<Cuboid> = HashSet::new();
-    for (i, instruction) in input.into_iter().enumerate() {
-        println!("{}, {:?}", i, instruction);
-        println!("The set of cuboids is now of size {}", cuboids.len());
-        match instruction {
-            Instruction { action: Action::On, cuboid} => {
-                add_and_remove_overlapping(&mut cuboids, cuboid);
-            },
-            Instruction { action: Action::Off, cuboid } => {
-                turn_things_off(&mut cuboids, cuboid);
-            }
-        }
+    let mut reactor = Reactor::new();
+    for instruction in input {
+        reactor.apply(instruction);
     }
-    count_on(&cuboids)
+    reactor.count_on()
 }
 
 fn count_on(cuboids: &HashSet<Cuboid>) -> usize {
-    cuboids
-        .iter()
-        .map(
-            |Cuboid {
-                 x_range: (x1, x2),
-                 y_range: (y1, y2),
-                 z_range: (z1, z2),
-             }| usize::try_from((*x2 - *x1 + 1) * (*y2 - *y1 + 1) * (*z2 - *z1 + 1)).unwrap(),
-        )
-        .sum()
+    cuboids.iter().map(Cuboid::volume).sum()
+}
+
+/// The state of the reactor after some reboot steps, kept as a set of disjoint lit cuboids.
+/// Every applied step remembers which cuboids it removed and added so that it can be undone.
+#[derive(Debug, Clone, Default)]
+pub struct Reactor {
+    cuboids: HashSet<Cuboid>,
+    history: Vec<Step>,
+}
+
+#[derive(Debug, Clone)]
+struct Step {
+    instruction: Instruction,
+    removed: Vec<Cuboid>,
+    added: Vec<Cuboid>,
+}
+
+impl Reactor {
+    pub fn new() -> Reactor {
+        Reactor::default()
+    }
+
+    pub fn apply(&mut self, instruction: Instruction) {
+        let (removed, added) = match instruction {
+            Instruction {
+                action: Action::On,
+                cuboid,
+            } => add_and_remove_overlapping(&mut self.cuboids, cuboid),
+            Instruction {
+                action: Action::Off,
+                cuboid,
+            } => turn_things_off(&mut self.cuboids, cuboid),
+        };
+        self.history.push(Step {
+            instruction,
+            removed,
+            added,
+        });
+    }
+
+    /// Reverts the most recently applied instruction, returning it, or `None` if nothing has been applied.
+    #[allow(dead_code)]
+    pub fn undo(&mut self) -> Option<Instruction> {
+        let step = self.history.pop()?;
+        for cuboid in &step.added {
+            self.cuboids.remove(cuboid);
+        }
+        self.cuboids.extend(step.removed);
+        Some(step.instruction)
+    }
+
+    #[allow(dead_code)]
+    pub fn applied(&self) -> impl Iterator<Item = &Instruction> {
+        self.history.iter().map(|step| &step.instruction)
+    }
+
+    #[allow(dead_code)]
+    pub fn is_on(&self, point: (i64, i64, i64)) -> bool {
+        self.cuboids.iter().any(|cuboid| cuboid.contains(point))
+    }
+
+    pub fn count_on(&self) -> usize {
+        count_on(&self.cuboids)
+    }
+
+    #[allow(dead_code)]
+    pub fn count_on_within(&self, region: Cuboid) -> usize {
+        self.cuboids
+            .iter()
+            .filter_map(|cuboid| cuboid.intersection(&region))
+            .map(|overlap| overlap.volume())
+            .sum()
+    }
+
+    /// The smallest cuboid containing every lit cell, or `None` if everything is off.
+    #[allow(dead_code)]
+    pub fn bounding_box(&self) -> Option<Cuboid> {
+        self.cuboids.iter().copied().reduce(|acc, cuboid| Cuboid {
+            x_range: (acc.x_range.0.min(cuboid.x_range.0), acc.x_range.1.max(cuboid.x_range.1)),
+            y_range: (acc.y_range.0.min(cuboid.y_range.0), acc.y_range.1.max(cuboid.y_range.1)),
+            z_range: (acc.z_range.0.min(cuboid.z_range.0), acc.z_range.1.max(cuboid.z_range.1)),
+        })
+    }
+
+    /// The lit cells as non-overlapping cuboids, in no particular order.
+    #[allow(dead_code)]
+    pub fn lit_cuboids(&self) -> impl Iterator<Item = &Cuboid> {
+        self.cuboids.iter()
+    }
+}
+
+impl From<&[Instruction]> for Reactor {
+    fn from(instructions: &[Instruction]) -> Self {
+        let mut reactor = Reactor::new();
+        for instruction in instructions {
+            reactor.apply(*instruction);
+        }
+        reactor
+    }
 }
 
 fn ranges_overlap((min, max): (i64, i64), (min1, max1): (i64, i64)) -> bool {
@@ -296,16 +404,16 @@ fn get_overlap_of_ranges(existing: (i64, i64), new: (i64, i64)) -> OverlapRanges
     }
 }
 
-fn add_and_remove_overlapping(cuboids: &mut HashSet<Cuboid>, to_add: Cuboid) {
-    let mut problem_cuboids: HashSet<_>  = cuboids.iter().copied().filter(|cuboid| check_cubes_overlap(*cuboid, to_add)).collect();
-    for cuboid in &problem_cuboids {
+/// Returns the cuboids it took out of the set and those it put in.
+fn add_and_remove_overlapping(cuboids: &mut HashSet<Cuboid>, to_add: Cuboid) -> (Vec<Cuboid>, Vec<Cuboid>) {
+    let removed: Vec<_> = cuboids.iter().copied().filter(|cuboid| check_cubes_overlap(*cuboid, to_add)).collect();
+    for cuboid in &removed {
         cuboids.remove(cuboid);
     }
 
+    let mut problem_cuboids: HashSet<_> = removed.iter().copied().collect();
     problem_cuboids.insert(to_add);
 
-    println!("There are {} problem cuboids", problem_cuboids.len());
-
     while problem_cuboids.iter().combinations(2).any(|items| check_cubes_overlap(*items[0], *items[1])) {
         let to_fix = problem_cuboids
             .iter()
@@ -324,21 +432,25 @@ fn add_and_remove_overlapping(cuboids: &mut HashSet<Cuboid>, to_add: Cuboid) {
         }
     }
 
-    for cuboid in problem_cuboids.drain() {
-        cuboids.insert(cuboid);
-    }
+    let added: Vec<_> = problem_cuboids.into_iter().collect();
+    cuboids.extend(added.iter().copied());
+    (removed, added)
 }
 
-fn turn_things_off(cuboids: &mut HashSet<Cuboid>, off_boid: Cuboid) {
+/// Returns the cuboids it took out of the set and those it put in.
+fn turn_things_off(cuboids: &mut HashSet<Cuboid>, off_boid: Cuboid) -> (Vec<Cuboid>, Vec<Cuboid>) {
     let need_altered: Vec<_> = cuboids.iter().copied().filter(|boid| check_cubes_overlap(*boid, off_boid)).collect();
-    for cuboid in need_altered {
-        cuboids.remove(&cuboid);
-        let overlap = get_overlap(&off_boid, &cuboid);
+    let mut added = vec![];
+    for cuboid in &need_altered {
+        cuboids.remove(cuboid);
+        let overlap = get_overlap(&off_boid, cuboid);
         let to_add = get_new_cuboids_ranges(overlap);
         for cuboid in to_add {
             cuboids.insert(cuboid);
+            added.push(cuboid);
         }
     }
+    (need_altered, added)
 }
 
 fn get_overlap(existing: &Cuboid, new: &Cuboid) -> Overlap {
@@ -556,6 +668,61 @@ mod tests {
         assert_eq!(count_on(&on_cuboids), 8);
     }
 
+    #[test]
+    fn test_reactor_matches_part_one() {
+        let input = parse_from_str(TEST_INPUT);
+        let reactor = Reactor::from(input.as_slice());
+        assert_eq!(
+            reactor.count_on_within(Cuboid::new((-50, 50), (-50, 50), (-50, 50))),
+            590784
+        );
+        assert!(reactor
+            .lit_cuboids()
+            .combinations(2)
+            .all(|pair| !check_cubes_overlap(*pair[0], *pair[1])));
+    }
+
+    #[test]
+    fn test_reactor_queries() {
+        let input = vec![
+            instruction_from_line("on x=10..12,y=10..12,z=10..12"),
+            instruction_from_line("on x=11..13,y=11..13,z=11..13"),
+            instruction_from_line("off x=9..11,y=9..11,z=9..11"),
+            instruction_from_line("on x=10..10,y=10..10,z=10..10"),
+        ];
+        let reactor = Reactor::from(input.as_slice());
+        assert_eq!(reactor.count_on(), 39);
+        assert!(reactor.is_on((10, 10, 10)));
+        assert!(!reactor.is_on((11, 11, 11)));
+        assert!(reactor.is_on((13, 13, 13)));
+        assert!(!reactor.is_on((14, 13, 13)));
+        assert_eq!(reactor.count_on_within(Cuboid::new((12, 20), (12, 20), (12, 20))), 8);
+        assert_eq!(reactor.bounding_box(), Some(Cuboid::new((10, 13), (10, 13), (10, 13))));
+        assert_eq!(Reactor::new().bounding_box(), None);
+    }
+
+    #[test]
+    fn test_reactor_undo() {
+        let input = vec![
+            instruction_from_line("on x=10..12,y=10..12,z=10..12"),
+            instruction_from_line("on x=11..13,y=11..13,z=11..13"),
+            instruction_from_line("off x=9..11,y=9..11,z=9..11"),
+        ];
+        let mut reactor = Reactor::from(input.as_slice());
+        assert_eq!(reactor.count_on(), 38);
+        assert_eq!(reactor.undo(), Some(input[2]));
+        assert_eq!(reactor.count_on(), 46);
+        assert_eq!(reactor.applied().count(), 2);
+        reactor.apply(input[2]);
+        assert_eq!(reactor.count_on(), 38);
+        assert_eq!(reactor.undo(), Some(input[2]));
+        assert_eq!(reactor.undo(), Some(input[1]));
+        assert_eq!(reactor.count_on(), 27);
+        assert_eq!(reactor.undo(), Some(input[0]));
+        assert_eq!(reactor.count_on(), 0);
+        assert_eq!(reactor.undo(), None);
+    }
+
     #[test]
     #[ignore]
     fn check_any_overlapping_offs_in_input() {
@@ -1,114 +1,209 @@
 use hashbrown::{HashMap, HashSet};
-use std::collections::VecDeque;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
 use std::fmt::{Display, Formatter};
+use std::fs::read_to_string;
 
-pub fn solve_part_1() -> Result<(), ()> {
-    println!("{}", part_one());
-    Ok(())
+/// How to look for the cheapest way of organising the amphipods.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Search {
+    /// Branch and bound over every sequence of moves, without remembering visited states.
+    DepthFirst,
+    /// A* over distinct burrow states, guided by `min_energy_to_finish`.
+    BestFirst,
 }
 
-pub fn solve_part_2() -> Result<(), ()> {
-    println!("{}", part_two());
+pub fn solve_part_1(search: Search, replay: bool) -> Result<(), ()> {
+    let input = read_to_string("./inputs/day23.txt").unwrap();
+    if replay {
+        replay_best_moves(&input);
+    } else {
+        println!("{}", part_one(&input, search));
+    }
     Ok(())
 }
 
-fn part_one() -> usize {
-    // #############
-    // #...........#
-    // ###B#B#C#D###
-    //   #D#C#A#A#
-    //   #########
+pub fn solve_part_2(search: Search, replay: bool) -> Result<(), ()> {
+    let input = unfold(&read_to_string("./inputs/day23.txt").unwrap()).map_err(|error| println!("{}", error))?;
+    if replay {
+        replay_best_moves(&input);
+    } else {
+        println!("{}", part_one(&input, search));
+    }
+    Ok(())
+}
 
-    let map = grotto_map();
+fn part_one(input: &str, search: Search) -> usize {
+    let (map, amphis) = parse_from_str(input);
     let grotto = Grotto {
-        active_amphis: vec![
-            Amphipod::B(1, 2),
-            Amphipod::D(2, 2),
-            Amphipod::B(1, 4),
-            Amphipod::C(2, 4),
-            Amphipod::C(1, 6),
-            Amphipod::A(2, 6),
-            Amphipod::D(1, 8),
-            Amphipod::A(2, 8),
-        ]
-        .into_iter()
-        .collect(),
+        active_amphis: amphis.into_iter().collect(),
         finished_amphis: vec![].into_iter().collect(),
         current_score: 0,
-        map_depth: 2,
+        map_depth: map.depth,
         map: &map,
     };
-    let mut min_score: usize = usize::MAX;
-    get_min_score(grotto, &mut min_score);
-    min_score
+    match search {
+        Search::DepthFirst => {
+            let mut min_score: usize = usize::MAX;
+            get_min_score(grotto, &mut min_score);
+            min_score
+        }
+        Search::BestFirst => {
+            let (min_score, _) = get_min_score_best_first(grotto).expect("The amphipods can't be organised");
+            min_score
+        }
+    }
 }
 
-fn part_two() -> usize {
-    // #############
-    // #...........#
-    // ###B#B#C#D###
-    //   #D#C#B#A#
-    //   #D#B#A#C#
-    //   #D#C#A#A#
-    //   #########
-
-    let map = bigger_grotto_map();
-    let grotto = Grotto {
-        active_amphis: vec![
-            Amphipod::B(1, 2),
-            Amphipod::D(2, 2),
-            Amphipod::D(3, 2),
-            Amphipod::D(4, 2),
-            Amphipod::B(1, 4),
-            Amphipod::C(2, 4),
-            Amphipod::B(3, 4),
-            Amphipod::C(4, 4),
-            Amphipod::C(1, 6),
-            Amphipod::B(2, 6),
-            Amphipod::A(3, 6),
-            Amphipod::A(4, 6),
-            Amphipod::D(1, 8),
-            Amphipod::A(2, 8),
-            Amphipod::C(3, 8),
-            Amphipod::A(4, 8),
-        ]
-        .into_iter()
-        .collect(),
+/// Finds the cheapest way of organising the amphipods and prints the burrow after each of its moves.
+fn replay_best_moves(input: &str) {
+    let (map, amphis) = parse_from_str(input);
+    let mut grotto = Grotto {
+        active_amphis: amphis.into_iter().collect(),
         finished_amphis: vec![].into_iter().collect(),
         current_score: 0,
-        map_depth: 4,
+        map_depth: map.depth,
         map: &map,
     };
-    let mut min_score: usize = usize::MAX;
-    get_min_score(grotto, &mut min_score);
-    min_score
+    grotto.move_finished_amphis();
+    let (min_score, moves) = get_min_score_best_first(grotto.clone()).expect("The amphipods can't be organised");
+    println!("Starting state:{}\n", grotto);
+    for (i, to_make) in moves.iter().enumerate() {
+        grotto = make_move(&grotto, to_make);
+        println!(
+            "Move {}: {}, total {}{}\n",
+            i + 1,
+            to_make,
+            grotto.current_score,
+            grotto
+        );
+    }
+    println!("Solution: {}", min_score);
 }
 
-#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Copy, Clone, Hash)]
-enum Amphipod {
-    A(usize, usize),
-    B(usize, usize),
-    C(usize, usize),
-    D(usize, usize),
+#[allow(dead_code)]
+fn part_two(input: &str, search: Search) -> Result<usize, String> {
+    Ok(part_one(&unfold(input)?, search))
 }
-impl Display for Amphipod {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::A(_, _) => write!(f, "A"),
-            Self::B(_, _) => write!(f, "B"),
-            Self::C(_, _) => write!(f, "C"),
-            Self::D(_, _) => write!(f, "D"),
+
+/// Inserts the two extra rows from the folded-up part of the diagram below the first row of the rooms, each a
+/// copy of that row with the folded-up amphipods in its spaces. Those rows are for four rooms, so nothing else
+/// can be unfolded.
+fn unfold(input: &str) -> Result<String, String> {
+    let rooms = parse_from_str(input).0.rooms.len();
+    if rooms != 4 {
+        return Err(format!("Only a burrow with 4 rooms can be unfolded, not {}", rooms));
+    }
+    let is_space = |c: char| !matches!(c, '#' | ' ');
+    let mut lines: Vec<String> = input.lines().map(String::from).collect();
+    let first_room_row = (0..lines.len())
+        .filter(|row| lines[*row].contains(is_space))
+        .nth(1)
+        .ok_or("The burrow has no rooms")?;
+    for (i, folded) in ["DCBA", "DBAC"].iter().enumerate() {
+        let mut folded = folded.chars();
+        let row = lines[first_room_row]
+            .chars()
+            .map(|c| if is_space(c) { folded.next().unwrap_or(c) } else { c })
+            .collect();
+        lines.insert(first_room_row + 1 + i, row);
+    }
+    Ok(lines.join("\n"))
+}
+
+/// Parses a burrow diagram into its layout and the amphipods within it. Every non-wall character is a
+/// space, the first row containing spaces is the hallway, and the rooms below it belong to amphipods
+/// `A`, `B`, `C`, ... in order from left to right.
+fn parse_from_str(input: &str) -> (Burrow, Vec<Amphipod>) {
+    let cells: Vec<(usize, usize, char)> = input
+        .lines()
+        .enumerate()
+        .flat_map(|(row, line)| line.chars().enumerate().map(move |(col, c)| (row, col, c)))
+        .filter(|(_, _, c)| !matches!(c, '#' | ' '))
+        .collect();
+    let hallway_row = cells
+        .iter()
+        .map(|(row, _, _)| *row)
+        .min()
+        .expect("No spaces in the burrow");
+    let hallway_start = cells
+        .iter()
+        .filter(|(row, _, _)| *row == hallway_row)
+        .map(|(_, col, _)| *col)
+        .min()
+        .unwrap();
+    let to_location = |row: usize, col: usize| {
+        (
+            row - hallway_row,
+            col.checked_sub(hallway_start).expect("Room to the left of the hallway"),
+        )
+    };
+
+    let map = Burrow::from_spaces(cells.iter().map(|(row, col, _)| to_location(*row, *col)).collect());
+    let amphis = cells
+        .into_iter()
+        .filter(|(_, _, c)| *c != '.')
+        .map(|(row, col, c)| match c {
+            'A'..='Z' if usize::from(c as u8 - b'A') < map.rooms.len() => Amphipod::new(c, to_location(row, col)),
+            _ => panic!("You wot? No room for '{}'", c),
+        })
+        .collect();
+    (map, amphis)
+}
+
+/// The shape of the burrow: which spaces neighbour each other and the column of each amphipod type's room.
+#[derive(Debug, Clone)]
+struct Burrow {
+    adjacency: HashMap<(usize, usize), Vec<(usize, usize)>>,
+    rooms: Vec<usize>,
+    width: usize,
+    depth: usize,
+}
+
+impl Burrow {
+    fn from_spaces(spaces: HashSet<(usize, usize)>) -> Burrow {
+        let adjacency = spaces
+            .iter()
+            .map(|&(y, x)| {
+                let neighbours = [(y.wrapping_sub(1), x), (y + 1, x), (y, x.wrapping_sub(1)), (y, x + 1)]
+                    .into_iter()
+                    .filter(|neighbour| spaces.contains(neighbour))
+                    .collect();
+                ((y, x), neighbours)
+            })
+            .collect();
+        let mut rooms: Vec<usize> = spaces.iter().filter(|loc| is_room(**loc)).map(|(_, x)| *x).collect();
+        rooms.sort_unstable();
+        rooms.dedup();
+        Burrow {
+            adjacency,
+            rooms,
+            width: spaces.iter().map(|(_, x)| x + 1).max().unwrap_or(0),
+            depth: spaces.iter().map(|(y, _)| *y).max().unwrap_or(0),
         }
     }
+
+    fn room_for(&self, amphipod: &Amphipod) -> usize {
+        self.rooms[amphipod.type_index()]
+    }
+
+    fn is_doorway(&self, (y, x): (usize, usize)) -> bool {
+        y == 0 && self.rooms.contains(&x)
+    }
 }
 
-fn default_char_for_row((y, x): (usize, usize)) -> &'static str {
-    match (y, x) {
-        (0, _) => ".",
-        (_, 2) | (_, 4) | (_, 6) | (_, 8) => ".",
-        _ => "#",
+#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Copy, Clone, Hash)]
+struct Amphipod {
+    kind: char,
+    y: usize,
+    x: usize,
+}
+impl Display for Amphipod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.kind)
     }
 }
+
 impl<'a> Display for Grotto<'a> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let positions_to_letters: HashMap<(usize, usize), String> = self
@@ -116,18 +211,19 @@ impl<'a> Display for Grotto<'a> {
             .into_iter()
             .map(|amphi| (amphi.destructure_location(), format!("{}", amphi)))
             .collect();
-        println!("{:?}", self.all_amphis());
-        println!("{:?}", self.active_amphis);
-        println!("{:?}", self.finished_amphis);
         let mut result = String::new();
-        for y in 0..=4 {
+        for y in 0..=self.map.depth {
             result.push('\n');
-            for x in 0..=10 {
+            for x in 0..self.map.width {
                 result.push_str(
                     positions_to_letters
                         .get(&(y, x))
                         .map(|letter| letter.as_str())
-                        .unwrap_or_else(|| default_char_for_row((y, x))),
+                        .unwrap_or(if self.map.adjacency.contains_key(&(y, x)) {
+                            "."
+                        } else {
+                            "#"
+                        }),
                 );
             }
         }
@@ -136,49 +232,33 @@ impl<'a> Display for Grotto<'a> {
 }
 
 impl Amphipod {
+    fn new(kind: char, (y, x): (usize, usize)) -> Amphipod {
+        Amphipod { kind, y, x }
+    }
+
     fn destructure_location(&self) -> (usize, usize) {
-        match self {
-            Amphipod::A(y, x) => (*y, *x),
-            Amphipod::B(y, x) => (*y, *x),
-            Amphipod::C(y, x) => (*y, *x),
-            Amphipod::D(y, x) => (*y, *x),
-        }
+        (self.y, self.x)
     }
-    fn in_new_loc(&self, (y, x): (usize, usize)) -> Amphipod {
-        match self {
-            Amphipod::A(_, _) => Amphipod::A(y, x),
-            Amphipod::B(_, _) => Amphipod::B(y, x),
-            Amphipod::C(_, _) => Amphipod::C(y, x),
-            Amphipod::D(_, _) => Amphipod::D(y, x),
-        }
+
+    fn in_new_loc(&self, location: (usize, usize)) -> Amphipod {
+        Amphipod::new(self.kind, location)
     }
 
     fn same_type(&self, rhs: &Amphipod) -> bool {
-        match self {
-            Amphipod::A(_, _) => matches!(rhs, Amphipod::A(_, _)),
-            Amphipod::B(_, _) => matches!(rhs, Amphipod::B(_, _)),
-            Amphipod::C(_, _) => matches!(rhs, Amphipod::C(_, _)),
-            Amphipod::D(_, _) => matches!(rhs, Amphipod::D(_, _)),
-        }
+        self.kind == rhs.kind
+    }
+
+    /// `A` is type 0, `B` is type 1 and so on; this is also the index of the type's room.
+    fn type_index(&self) -> usize {
+        usize::from(self.kind as u8 - b'A')
     }
 
     fn cost_of_move(&self, spaces: usize) -> usize {
-        match self {
-            Amphipod::A(_, _) => spaces,
-            Amphipod::B(_, _) => spaces * 10,
-            Amphipod::C(_, _) => spaces * 100,
-            Amphipod::D(_, _) => spaces * 1000,
-        }
+        spaces * 10usize.pow(self.type_index() as u32)
     }
 
     fn in_final_location(&self, grotto: &Grotto) -> bool {
-        let in_own_room = match self {
-            Amphipod::A(y, x) if *y > 0 && *x == 2 => true,
-            Amphipod::B(y, x) if *y > 0 && *x == 4 => true,
-            Amphipod::C(y, x) if *y > 0 && *x == 6 => true,
-            Amphipod::D(y, x) if *y > 0 && *x == 8 => true,
-            _ => false,
-        };
+        let in_own_room = self.y > 0 && self.x == grotto.map.room_for(self);
 
         if !in_own_room {
             return false;
@@ -198,6 +278,19 @@ struct Move {
     cost: usize,
 }
 
+impl Display for Move {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} from {:?} to {:?} costs {}",
+            self.from,
+            self.from.destructure_location(),
+            self.to.destructure_location(),
+            self.cost
+        )
+    }
+}
+
 impl Move {
     fn new(from: Amphipod, to: ((usize, usize), usize)) -> Self {
         Move {
@@ -213,7 +306,7 @@ struct Grotto<'a> {
     active_amphis: HashSet<Amphipod>,
     finished_amphis: HashSet<Amphipod>,
     current_score: usize,
-    map: &'a HashMap<(usize, usize), Vec<(usize, usize)>>,
+    map: &'a Burrow,
     map_depth: usize,
 }
 
@@ -237,11 +330,68 @@ impl<'a> Grotto<'a> {
     }
 
     fn is_finished(&self) -> bool {
-        self.finished_amphis.len() == if self.map_depth == 2 { 8 } else { 16 }
+        self.active_amphis.is_empty()
+    }
+
+    /// Every amphipod in the burrow in a canonical order, so that two grottos with the amphipods in the same
+    /// places have equal states no matter how they got there.
+    fn state(&self) -> Vec<Amphipod> {
+        let mut state: Vec<_> = self.all_amphis().into_iter().collect();
+        state.sort_unstable();
+        state
+    }
+
+    fn with_state(&self, state: &[Amphipod], current_score: usize) -> Grotto<'a> {
+        let mut grotto = Grotto {
+            active_amphis: state.iter().copied().collect(),
+            finished_amphis: HashSet::new(),
+            current_score,
+            map: self.map,
+            map_depth: self.map_depth,
+        };
+        grotto.move_finished_amphis();
+        grotto
+    }
+
+    /// A lower bound on the energy still needed, ignoring any amphipods that are in the way. An unfinished
+    /// amphipod in its own room with only its own type or free spaces below it just has to shuffle down past
+    /// the free spaces. Every other unfinished amphipod has to walk to the doorway of its room, and then they
+    /// have to fill the free spaces in it.
+    fn min_energy_to_finish(&self) -> usize {
+        let occupied: HashMap<(usize, usize), char> = self
+            .all_amphis()
+            .into_iter()
+            .map(|amphi| (amphi.destructure_location(), amphi.kind))
+            .collect();
+        let mut to_doorways = 0;
+        let mut still_to_enter: HashMap<char, usize> = HashMap::new();
+        for amphi in &self.active_amphis {
+            let room = self.map.room_for(amphi);
+            let spaces = if amphi.y > 0 && amphi.x == room {
+                let below: Vec<Option<&char>> = (amphi.y + 1..=self.map_depth)
+                    .filter(|y| self.map.adjacency.contains_key(&(*y, room)))
+                    .map(|y| occupied.get(&(y, room)))
+                    .collect();
+                if below.iter().flatten().all(|kind| **kind == amphi.kind) {
+                    to_doorways += amphi.cost_of_move(below.iter().filter(|kind| kind.is_none()).count());
+                    continue;
+                }
+                amphi.y + 2
+            } else {
+                amphi.y + amphi.x.abs_diff(room)
+            };
+            to_doorways += amphi.cost_of_move(spaces);
+            *still_to_enter.entry(amphi.kind).or_default() += 1;
+        }
+        let into_rooms: usize = still_to_enter
+            .into_iter()
+            .map(|(kind, count)| Amphipod::new(kind, (0, 0)).cost_of_move(count * (count + 1) / 2))
+            .sum();
+        to_doorways + into_rooms
     }
 }
 
-fn make_move<'a>(grotto: &'a Grotto, move_to_make: &Move) -> Grotto<'a> {
+fn make_move<'a>(grotto: &Grotto<'a>, move_to_make: &Move) -> Grotto<'a> {
     let mut new_grotto = grotto.clone();
     new_grotto.active_amphis.remove(&move_to_make.from);
     new_grotto.active_amphis.insert(move_to_make.to);
@@ -279,7 +429,9 @@ fn get_min_score(mut grotto: Grotto, min_score: &mut usize) -> Option<usize> {
         }
         if new_grotto.is_finished() && new_grotto.current_score < *min_score {
             *min_score = new_grotto.current_score;
-            dbg!(*min_score);
+            if DEBUG {
+                println!("New best: {}", min_score);
+            }
         } else {
             get_min_score(new_grotto, min_score);
         }
@@ -287,85 +439,62 @@ fn get_min_score(mut grotto: Grotto, min_score: &mut usize) -> Option<usize> {
     Some(*min_score)
 }
 
-fn grotto_map() -> HashMap<(usize, usize), Vec<(usize, usize)>> {
-    vec![
-        ((0, 0), vec![(0, 1)]),
-        ((0, 1), vec![(0, 0), (0, 2)]),
-        ((0, 2), vec![(0, 1), (0, 3), (1, 2)]),
-        ((0, 3), vec![(0, 2), (0, 4)]),
-        ((0, 4), vec![(0, 3), (0, 5), (1, 4)]),
-        ((0, 5), vec![(0, 4), (0, 6)]),
-        ((0, 6), vec![(0, 5), (0, 7), (1, 6)]),
-        ((0, 7), vec![(0, 6), (0, 8)]),
-        ((0, 8), vec![(0, 7), (0, 9), (1, 8)]),
-        ((0, 9), vec![(0, 8), (0, 10)]),
-        ((0, 10), vec![(0, 9)]),
-        ((1, 2), vec![(0, 2), (2, 2)]),
-        ((2, 2), vec![(1, 2)]),
-        ((1, 4), vec![(0, 4), (2, 4)]),
-        ((2, 4), vec![(1, 4)]),
-        ((1, 6), vec![(0, 6), (2, 6)]),
-        ((2, 6), vec![(1, 6)]),
-        ((1, 8), vec![(0, 8), (2, 8)]),
-        ((2, 8), vec![(1, 8)]),
-    ]
-    .into_iter()
-    .collect()
-}
-
-fn bigger_grotto_map() -> HashMap<(usize, usize), Vec<(usize, usize)>> {
-    vec![
-        ((0, 0), vec![(0, 1)]),
-        ((0, 1), vec![(0, 0), (0, 2)]),
-        ((0, 2), vec![(0, 1), (0, 3), (1, 2)]),
-        ((0, 3), vec![(0, 2), (0, 4)]),
-        ((0, 4), vec![(0, 3), (0, 5), (1, 4)]),
-        ((0, 5), vec![(0, 4), (0, 6)]),
-        ((0, 6), vec![(0, 5), (0, 7), (1, 6)]),
-        ((0, 7), vec![(0, 6), (0, 8)]),
-        ((0, 8), vec![(0, 7), (0, 9), (1, 8)]),
-        ((0, 9), vec![(0, 8), (0, 10)]),
-        ((0, 10), vec![(0, 9)]),
-        ((1, 2), vec![(0, 2), (2, 2)]),
-        ((2, 2), vec![(1, 2), (3, 2)]),
-        ((3, 2), vec![(2, 2), (4, 2)]),
-        ((4, 2), vec![(3, 2)]),
-        ((1, 4), vec![(0, 4), (2, 4)]),
-        ((2, 4), vec![(1, 4), (3, 4)]),
-        ((3, 4), vec![(2, 4), (4, 4)]),
-        ((4, 4), vec![(3, 4)]),
-        ((1, 6), vec![(0, 6), (2, 6)]),
-        ((2, 6), vec![(1, 6), (3, 6)]),
-        ((3, 6), vec![(2, 6), (4, 6)]),
-        ((4, 6), vec![(3, 6)]),
-        ((1, 8), vec![(0, 8), (2, 8)]),
-        ((2, 8), vec![(1, 8), (3, 8)]),
-        ((3, 8), vec![(2, 8), (4, 8)]),
-        ((4, 8), vec![(3, 8)]),
-    ]
-    .into_iter()
-    .collect()
+/// Returns the lowest possible score along with the moves that achieve it, or `None` if the amphipods can
+/// never all reach their rooms.
+fn get_min_score_best_first(mut grotto: Grotto) -> Option<(usize, Vec<Move>)> {
+    grotto.move_finished_amphis();
+    let mut best_scores: HashMap<Vec<Amphipod>, usize> = HashMap::new();
+    let mut came_from: HashMap<Vec<Amphipod>, (Vec<Amphipod>, Move)> = HashMap::new();
+    let mut to_explore = BinaryHeap::new();
+    best_scores.insert(grotto.state(), grotto.current_score);
+    to_explore.push(Reverse((
+        grotto.current_score + grotto.min_energy_to_finish(),
+        grotto.current_score,
+        grotto.state(),
+    )));
+
+    while let Some(Reverse((_, score, state))) = to_explore.pop() {
+        if best_scores[&state] < score {
+            continue;
+        }
+        let current = grotto.with_state(&state, score);
+        if current.is_finished() {
+            let mut moves = vec![];
+            let mut state = state;
+            while let Some((previous, made)) = came_from.remove(&state) {
+                moves.push(made);
+                state = previous;
+            }
+            moves.reverse();
+            return Some((score, moves));
+        }
+        for to_make in get_all_possible_moves(&current) {
+            let next = make_move(&current, &to_make);
+            let next_state = next.state();
+            if next.current_score < best_scores.get(&next_state).copied().unwrap_or(usize::MAX) {
+                best_scores.insert(next_state.clone(), next.current_score);
+                came_from.insert(next_state.clone(), (state.clone(), to_make));
+                to_explore.push(Reverse((
+                    next.current_score + next.min_energy_to_finish(),
+                    next.current_score,
+                    next_state,
+                )));
+            }
+        }
+    }
+    None
 }
 
 fn is_corridor((y, _): (usize, usize)) -> bool {
     y == 0
 }
 
-fn doorway((y, x): (usize, usize)) -> bool {
-    y == 0 && matches!(x, 2 | 4 | 6 | 8)
-}
-
 fn is_room((y, _): (usize, usize)) -> bool {
     y > 0
 }
 
-fn is_allowed_room(prospect: Amphipod) -> bool {
-    match prospect {
-        Amphipod::A(_, x) => x == 2,
-        Amphipod::B(_, x) => x == 4,
-        Amphipod::C(_, x) => x == 6,
-        Amphipod::D(_, x) => x == 8,
-    }
+fn is_allowed_room(prospect: Amphipod, grotto: &Grotto) -> bool {
+    prospect.x == grotto.map.room_for(&prospect)
 }
 
 fn is_occupied((y, x): (usize, usize), grotto: &Grotto) -> bool {
@@ -401,12 +530,12 @@ fn is_deepest_available(amphipod: Amphipod, grotto: &Grotto) -> bool {
 fn able_to_stop(current: Amphipod, prospect: Amphipod, grotto: &Grotto) -> bool {
     let prospect_location = prospect.destructure_location();
     let current_location = current.destructure_location();
-    if doorway(prospect_location) {
+    if grotto.map.is_doorway(prospect_location) {
         return false;
     }
 
     if is_room(prospect_location) {
-        if !is_allowed_room(prospect) {
+        if !is_allowed_room(prospect, grotto) {
             return false;
         }
         if room_contains_other_type(prospect, grotto) {
@@ -431,6 +560,7 @@ fn get_adjacent_unoccupied_spaces(
 ) -> VecDeque<((usize, usize), Distance)> {
     grotto
         .map
+        .adjacency
         .get(&location)
         .unwrap()
         .iter()
@@ -490,20 +620,203 @@ fn get_possible_moves(grotto: &Grotto, amphipod: Amphipod) -> Vec<Move> {
 mod tests {
     use super::*;
 
+    const EXAMPLE: &str = "\
+#############
+#...........#
+###B#C#B#D###
+  #A#D#C#A#
+  #########";
+
+    fn grotto_map() -> Burrow {
+        parse_from_str(EXAMPLE).0
+    }
+
+    fn bigger_grotto_map() -> Burrow {
+        parse_from_str(&unfold(EXAMPLE).unwrap()).0
+    }
+
+    #[test]
+    fn test_parse_burrow() {
+        let (map, amphis) = parse_from_str(EXAMPLE);
+        assert_eq!(map.rooms, vec![2, 4, 6, 8]);
+        assert_eq!(map.depth, 2);
+        assert_eq!(map.width, 11);
+        assert_eq!(map.adjacency.len(), 19);
+        assert_eq!(map.adjacency[&(0, 0)], vec![(0, 1)]);
+        assert_eq!(
+            map.adjacency[&(0, 4)].iter().copied().collect::<HashSet<_>>(),
+            vec![(0, 3), (0, 5), (1, 4)].into_iter().collect()
+        );
+        assert_eq!(
+            map.adjacency[&(1, 8)].iter().copied().collect::<HashSet<_>>(),
+            vec![(0, 8), (2, 8)].into_iter().collect()
+        );
+        assert_eq!(map.adjacency[&(2, 8)], vec![(1, 8)]);
+        assert_eq!(
+            amphis.into_iter().collect::<HashSet<_>>(),
+            vec![
+                Amphipod::new('A', (2, 2)),
+                Amphipod::new('B', (1, 2)),
+                Amphipod::new('D', (2, 4)),
+                Amphipod::new('C', (1, 4)),
+                Amphipod::new('C', (2, 6)),
+                Amphipod::new('B', (1, 6)),
+                Amphipod::new('A', (2, 8)),
+                Amphipod::new('D', (1, 8)),
+            ]
+            .into_iter()
+            .collect()
+        );
+
+        let bigger = bigger_grotto_map();
+        assert_eq!(bigger.depth, 4);
+        assert_eq!(bigger.adjacency.len(), 27);
+    }
+
+    #[test]
+    fn test_part_one() {
+        assert_eq!(part_one(EXAMPLE, Search::BestFirst), 12521);
+    }
+
+    #[test]
+    fn test_part_two() {
+        assert_eq!(part_two(EXAMPLE, Search::BestFirst), Ok(44169));
+    }
+
+    #[test]
+    fn test_best_moves_replay() {
+        let (map, amphis) = parse_from_str(EXAMPLE);
+        let mut grotto = Grotto {
+            active_amphis: amphis.into_iter().collect(),
+            finished_amphis: vec![].into_iter().collect(),
+            current_score: 0,
+            map: &map,
+            map_depth: 2,
+        };
+        let (min_score, moves) = get_min_score_best_first(grotto.clone()).unwrap();
+        assert_eq!(min_score, 12521);
+        assert_eq!(moves.iter().map(|made| made.cost).sum::<usize>(), 12521);
+        grotto.move_finished_amphis();
+        for to_make in &moves {
+            assert!(get_all_possible_moves(&grotto).contains(to_make));
+            grotto = make_move(&grotto, to_make);
+        }
+        assert!(grotto.is_finished());
+        assert_eq!(grotto.current_score, 12521);
+    }
+
+    #[test]
+    fn test_grotto_display() {
+        let (map, amphis) = parse_from_str(EXAMPLE);
+        let grotto = Grotto {
+            active_amphis: amphis.into_iter().collect(),
+            finished_amphis: vec![].into_iter().collect(),
+            current_score: 0,
+            map: &map,
+            map_depth: 2,
+        };
+        assert_eq!(format!("{}", grotto), "\n...........\n##B#C#B#D##\n##A#D#C#A##");
+    }
+
+    #[test]
+    fn test_searches_agree() {
+        assert_eq!(
+            part_one(EXAMPLE, Search::DepthFirst),
+            part_one(EXAMPLE, Search::BestFirst)
+        );
+    }
+
+    #[test]
+    fn test_min_energy_to_finish() {
+        let map = grotto_map();
+        let grotto = Grotto {
+            active_amphis: vec![
+                Amphipod::new('B', (0, 0)),
+                Amphipod::new('A', (1, 2)),
+                Amphipod::new('B', (2, 2)),
+            ]
+            .into_iter()
+            .collect(),
+            finished_amphis: vec![].into_iter().collect(),
+            current_score: 0,
+            map: &map,
+            map_depth: 2,
+        };
+        // Both Bs walk four spaces to their doorway and then fill both spaces in the room, while A has to
+        // step out of the way and back before taking the top space of its own room.
+        assert_eq!(grotto.min_energy_to_finish(), 40 + 40 + 30 + 3 + 1);
+    }
+
+    #[test]
+    fn test_three_room_burrow() {
+        let input = "\
+###########
+#.........#
+###A#C#B###
+  #######";
+        let (map, _) = parse_from_str(input);
+        assert_eq!(map.rooms, vec![2, 4, 6]);
+        assert_eq!(map.depth, 1);
+        assert_eq!(part_one(input, Search::BestFirst), 460);
+        assert_eq!(part_one(input, Search::DepthFirst), 460);
+    }
+
+    #[test]
+    fn test_gap_in_room() {
+        let input = "\
+#######
+#.....#
+##A#.##
+ #.#B#
+ #####";
+        let (map, amphis) = parse_from_str(input);
+        let mut grotto = Grotto {
+            active_amphis: amphis.into_iter().collect(),
+            finished_amphis: vec![].into_iter().collect(),
+            current_score: 0,
+            map_depth: map.depth,
+            map: &map,
+        };
+        grotto.move_finished_amphis();
+        // A only has to step down into the free space below it.
+        assert_eq!(grotto.min_energy_to_finish(), 1);
+        assert_eq!(part_one(input, Search::BestFirst), 1);
+        assert_eq!(part_one(input, Search::DepthFirst), 1);
+    }
+
+    #[test]
+    fn test_unfold_three_rooms() {
+        assert!(unfold("###########\n#.........#\n###A#C#B###\n  #######").is_err());
+    }
+
+    #[test]
+    fn test_unfold_elsewhere_in_the_diagram() {
+        let unfolded = unfold(EXAMPLE).unwrap();
+        assert_eq!(unfolded.lines().nth(3), Some("###D#C#B#A###"));
+        let shifted: String = EXAMPLE.lines().map(|line| format!("    {}\n", line)).collect();
+        let shifted = unfold(&format!("\n{}", shifted)).unwrap();
+        let (map, amphis) = parse_from_str(&unfolded);
+        let (shifted_map, shifted_amphis) = parse_from_str(&shifted);
+        assert_eq!(shifted_amphis, amphis);
+        assert_eq!(shifted_map.rooms, map.rooms);
+        assert_eq!(shifted_map.depth, 4);
+        assert_eq!(shifted_map.adjacency.len(), map.adjacency.len());
+    }
+
     #[test]
     fn get_min_score_of_grotto() {
         let map = grotto_map();
         let mut min = usize::MAX;
         let grotto = Grotto {
             active_amphis: vec![
-                Amphipod::A(2, 2),
-                Amphipod::B(1, 2),
-                Amphipod::D(2, 4),
-                Amphipod::C(1, 4),
-                Amphipod::C(2, 6),
-                Amphipod::B(1, 6),
-                Amphipod::A(2, 8),
-                Amphipod::D(1, 8),
+                Amphipod::new('A', (2, 2)),
+                Amphipod::new('B', (1, 2)),
+                Amphipod::new('D', (2, 4)),
+                Amphipod::new('C', (1, 4)),
+                Amphipod::new('C', (2, 6)),
+                Amphipod::new('B', (1, 6)),
+                Amphipod::new('A', (2, 8)),
+                Amphipod::new('D', (1, 8)),
             ]
             .into_iter()
             .collect(),
@@ -522,22 +835,22 @@ mod tests {
         let mut min = usize::MAX;
         let grotto = Grotto {
             active_amphis: vec![
-                Amphipod::B(1, 2),
-                Amphipod::D(2, 2),
-                Amphipod::D(3, 2),
-                Amphipod::A(4, 2),
-                Amphipod::C(1, 4),
-                Amphipod::C(2, 4),
-                Amphipod::B(3, 4),
-                Amphipod::D(4, 4),
-                Amphipod::B(1, 6),
-                Amphipod::B(2, 6),
-                Amphipod::A(3, 6),
-                Amphipod::C(4, 6),
-                Amphipod::D(1, 8),
-                Amphipod::A(2, 8),
-                Amphipod::C(3, 8),
-                Amphipod::A(4, 8),
+                Amphipod::new('B', (1, 2)),
+                Amphipod::new('D', (2, 2)),
+                Amphipod::new('D', (3, 2)),
+                Amphipod::new('A', (4, 2)),
+                Amphipod::new('C', (1, 4)),
+                Amphipod::new('C', (2, 4)),
+                Amphipod::new('B', (3, 4)),
+                Amphipod::new('D', (4, 4)),
+                Amphipod::new('B', (1, 6)),
+                Amphipod::new('B', (2, 6)),
+                Amphipod::new('A', (3, 6)),
+                Amphipod::new('C', (4, 6)),
+                Amphipod::new('D', (1, 8)),
+                Amphipod::new('A', (2, 8)),
+                Amphipod::new('C', (3, 8)),
+                Amphipod::new('A', (4, 8)),
             ]
             .into_iter()
             .collect(),
@@ -554,20 +867,22 @@ mod tests {
     fn test_get_unobstructed_spaces() {
         let map = grotto_map();
         let grotto = Grotto {
-            active_amphis: vec![Amphipod::A(2, 2), Amphipod::B(1, 2)].into_iter().collect(),
+            active_amphis: vec![Amphipod::new('A', (2, 2)), Amphipod::new('B', (1, 2))]
+                .into_iter()
+                .collect(),
             finished_amphis: vec![].into_iter().collect(),
             current_score: 0,
             map: &map,
             map_depth: 2,
         };
-        let unobstructed_spaces: Vec<(usize, usize)> = get_unobstructed_spaces(&grotto, Amphipod::A(2, 2))
+        let unobstructed_spaces: Vec<(usize, usize)> = get_unobstructed_spaces(&grotto, Amphipod::new('A', (2, 2)))
             .into_iter()
             .map(|(location, _)| location)
             .collect();
 
         assert_eq!(unobstructed_spaces, vec![]);
 
-        let unobstructed_spaces: HashSet<_> = get_unobstructed_spaces(&grotto, Amphipod::B(1, 2))
+        let unobstructed_spaces: HashSet<_> = get_unobstructed_spaces(&grotto, Amphipod::new('B', (1, 2)))
             .into_iter()
             .map(|(location, _)| location)
             .collect();
@@ -596,7 +911,7 @@ mod tests {
             .into_iter()
             .collect()
         );
-        let distances_with_spaces: HashSet<_> = get_unobstructed_spaces(&grotto, Amphipod::B(1, 2))
+        let distances_with_spaces: HashSet<_> = get_unobstructed_spaces(&grotto, Amphipod::new('B', (1, 2)))
             .into_iter()
             .collect();
         assert!(distances_with_spaces.contains(&((2, 8), 9)));
@@ -607,15 +922,17 @@ mod tests {
     fn test_get_possible_moves() {
         let map = grotto_map();
         let grotto = Grotto {
-            active_amphis: vec![Amphipod::A(2, 2), Amphipod::B(1, 2)].into_iter().collect(),
+            active_amphis: vec![Amphipod::new('A', (2, 2)), Amphipod::new('B', (1, 2))]
+                .into_iter()
+                .collect(),
             finished_amphis: vec![].into_iter().collect(),
             current_score: 0,
             map: &map,
             map_depth: 2,
         };
-        assert_eq!(get_possible_moves(&grotto, Amphipod::A(2, 2)), vec![]);
+        assert_eq!(get_possible_moves(&grotto, Amphipod::new('A', (2, 2))), vec![]);
         assert_eq!(
-            get_possible_moves(&grotto, Amphipod::B(1, 2))
+            get_possible_moves(&grotto, Amphipod::new('B', (1, 2)))
                 .into_iter()
                 .collect::<HashSet<_>>(),
             vec![
@@ -629,58 +946,64 @@ mod tests {
                 ((0, 10), 9)
             ]
             .into_iter()
-            .map(|item| Move::new(Amphipod::B(1, 2), item))
+            .map(|item| Move::new(Amphipod::new('B', (1, 2)), item))
             .collect::<HashSet<_>>()
         );
 
         let grotto = Grotto {
-            active_amphis: vec![Amphipod::A(0, 0), Amphipod::B(2, 2)].into_iter().collect(),
+            active_amphis: vec![Amphipod::new('A', (0, 0)), Amphipod::new('B', (2, 2))]
+                .into_iter()
+                .collect(),
             finished_amphis: vec![].into_iter().collect(),
             current_score: 0,
             map: &map,
             map_depth: 2,
         };
         assert_eq!(
-            get_possible_moves(&grotto, Amphipod::A(0, 0))
+            get_possible_moves(&grotto, Amphipod::new('A', (0, 0)))
                 .into_iter()
                 .collect::<HashSet<_>>(),
             vec![]
                 .into_iter()
-                .map(|item| Move::new(Amphipod::B(1, 2), item))
+                .map(|item| Move::new(Amphipod::new('B', (1, 2)), item))
                 .collect::<HashSet<_>>()
         );
 
         let grotto = Grotto {
-            active_amphis: vec![Amphipod::A(0, 0), Amphipod::B(0, 1)].into_iter().collect(),
+            active_amphis: vec![Amphipod::new('A', (0, 0)), Amphipod::new('B', (0, 1))]
+                .into_iter()
+                .collect(),
             finished_amphis: vec![].into_iter().collect(),
             current_score: 0,
             map: &map,
             map_depth: 2,
         };
         assert_eq!(
-            get_possible_moves(&grotto, Amphipod::A(0, 0))
+            get_possible_moves(&grotto, Amphipod::new('A', (0, 0)))
                 .into_iter()
                 .collect::<HashSet<_>>(),
             vec![]
                 .into_iter()
-                .map(|item| Move::new(Amphipod::B(1, 2), item))
+                .map(|item| Move::new(Amphipod::new('B', (1, 2)), item))
                 .collect::<HashSet<_>>()
         );
 
         let grotto = Grotto {
-            active_amphis: vec![Amphipod::A(0, 0), Amphipod::B(0, 8)].into_iter().collect(),
+            active_amphis: vec![Amphipod::new('A', (0, 0)), Amphipod::new('B', (0, 8))]
+                .into_iter()
+                .collect(),
             finished_amphis: vec![].into_iter().collect(),
             current_score: 0,
             map: &map,
             map_depth: 2,
         };
         assert_eq!(
-            get_possible_moves(&grotto, Amphipod::A(0, 0))
+            get_possible_moves(&grotto, Amphipod::new('A', (0, 0)))
                 .into_iter()
                 .collect::<HashSet<_>>(),
             vec![((2, 2), 4)]
                 .into_iter()
-                .map(|item| Move::new(Amphipod::A(0, 0), item))
+                .map(|item| Move::new(Amphipod::new('A', (0, 0)), item))
                 .collect::<HashSet<_>>()
         );
     }
@@ -692,10 +1015,10 @@ mod tests {
         let _min = usize::MAX;
         let grotto = Grotto {
             active_amphis: vec![
-                Amphipod::B(1, 2),
-                Amphipod::D(2, 2),
-                Amphipod::D(3, 2),
-                Amphipod::A(4, 2),
+                Amphipod::new('B', (1, 2)),
+                Amphipod::new('D', (2, 2)),
+                Amphipod::new('D', (3, 2)),
+                Amphipod::new('A', (4, 2)),
             ]
             .into_iter()
             .collect(),
@@ -704,26 +1027,28 @@ mod tests {
             map: &map,
             map_depth: 4,
         };
-        assert_eq!(get_possible_moves(&grotto, Amphipod::B(1, 2)), vec![]);
+        assert_eq!(get_possible_moves(&grotto, Amphipod::new('B', (1, 2))), vec![]);
     }
 
     #[test]
     fn check_score() {
         let map = grotto_map();
         let grotto = Grotto {
-            active_amphis: vec![Amphipod::A(0, 0), Amphipod::D(0, 10)].into_iter().collect(),
+            active_amphis: vec![Amphipod::new('A', (0, 0)), Amphipod::new('D', (0, 10))]
+                .into_iter()
+                .collect(),
             finished_amphis: vec![].into_iter().collect(),
             current_score: 0,
             map: &map,
             map_depth: 2,
         };
         println!("{}", grotto);
-        let set_of_moves = get_possible_moves(&grotto, Amphipod::D(0, 10))
+        let set_of_moves = get_possible_moves(&grotto, Amphipod::new('D', (0, 10)))
             .into_iter()
             .collect::<HashSet<_>>();
         assert!(set_of_moves.contains(&Move {
-            from: Amphipod::D(0, 10),
-            to: Amphipod::D(2, 8),
+            from: Amphipod::new('D', (0, 10)),
+            to: Amphipod::new('D', (2, 8)),
             cost: 4000
         }))
     }
@@ -732,7 +1057,7 @@ mod tests {
     fn check_get_all_possible_moves() {
         let map = grotto_map();
         let grotto = Grotto {
-            active_amphis: vec![Amphipod::A(0, 0)].into_iter().collect(),
+            active_amphis: vec![Amphipod::new('A', (0, 0))].into_iter().collect(),
             finished_amphis: vec![].into_iter().collect(),
             current_score: 0,
             map: &map,
@@ -740,17 +1065,19 @@ mod tests {
         };
         let possible_moves = get_all_possible_moves(&grotto).into_iter().collect::<HashSet<_>>();
         assert!(possible_moves.contains(&Move {
-            from: Amphipod::D(0, 10),
-            to: Amphipod::D(2, 8),
+            from: Amphipod::new('D', (0, 10)),
+            to: Amphipod::new('D', (2, 8)),
             cost: 4000
         }));
         assert!(possible_moves.contains(&Move {
-            from: Amphipod::A(0, 0),
-            to: Amphipod::A(2, 2),
+            from: Amphipod::new('A', (0, 0)),
+            to: Amphipod::new('A', (2, 2)),
             cost: 4
         }));
         let grotto = Grotto {
-            active_amphis: vec![Amphipod::A(0, 7), Amphipod::D(2, 2)].into_iter().collect(),
+            active_amphis: vec![Amphipod::new('A', (0, 7)), Amphipod::new('D', (2, 2))]
+                .into_iter()
+                .collect(),
             finished_amphis: vec![].into_iter().collect(),
             current_score: 0,
             map: &map,
@@ -758,22 +1085,22 @@ mod tests {
         };
         let possible_moves = get_all_possible_moves(&grotto).into_iter().collect::<HashSet<_>>();
         assert!(possible_moves.contains(&Move {
-            from: Amphipod::D(2, 2),
-            to: Amphipod::D(0, 0),
+            from: Amphipod::new('D', (2, 2)),
+            to: Amphipod::new('D', (0, 0)),
             cost: 4000
         }));
         assert!(!possible_moves
             .into_iter()
             .map(|example_move| (example_move.from, example_move.to))
-            .any(|amphis| amphis == (Amphipod::A(0, 7), Amphipod::A(2, 1))))
+            .any(|amphis| amphis == (Amphipod::new('A', (0, 7)), Amphipod::new('A', (2, 1)))))
     }
 
     #[test]
     fn check_failing_case() {
         let map = grotto_map();
         let grotto = Grotto {
-            active_amphis: vec![Amphipod::A(0, 0)].into_iter().collect(),
-            finished_amphis: vec![Amphipod::A(2, 2)].into_iter().collect(),
+            active_amphis: vec![Amphipod::new('A', (0, 0))].into_iter().collect(),
+            finished_amphis: vec![Amphipod::new('A', (2, 2))].into_iter().collect(),
             current_score: 0,
             map: &map,
             map_depth: 2,
@@ -781,13 +1108,13 @@ mod tests {
         let possible_moves = get_all_possible_moves(&grotto).into_iter().collect::<HashSet<_>>();
         assert_eq!(possible_moves.iter().count(), 1);
         assert!(possible_moves.contains(&Move {
-            from: Amphipod::A(0, 0),
-            to: Amphipod::A(1, 2),
+            from: Amphipod::new('A', (0, 0)),
+            to: Amphipod::new('A', (1, 2)),
             cost: 3
         }));
         assert!(!possible_moves.contains(&Move {
-            from: Amphipod::A(0, 0),
-            to: Amphipod::A(2, 2),
+            from: Amphipod::new('A', (0, 0)),
+            to: Amphipod::new('A', (2, 2)),
             cost: 4
         }));
     }
@@ -802,31 +1129,37 @@ mod tests {
             map: &map,
             map_depth: 2,
         };
-        grotto.active_amphis = vec![Amphipod::A(0, 0), Amphipod::D(0, 10)].into_iter().collect();
-        assert!(Amphipod::A(2, 2).in_final_location(&grotto));
-        assert!(!Amphipod::A(0, 0).in_final_location(&grotto));
+        grotto.active_amphis = vec![Amphipod::new('A', (0, 0)), Amphipod::new('D', (0, 10))]
+            .into_iter()
+            .collect();
+        assert!(Amphipod::new('A', (2, 2)).in_final_location(&grotto));
+        assert!(!Amphipod::new('A', (0, 0)).in_final_location(&grotto));
 
-        grotto.active_amphis = vec![Amphipod::A(1, 2), Amphipod::A(2, 2)].into_iter().collect();
-        assert!(Amphipod::A(1, 2).in_final_location(&grotto));
-        assert!(Amphipod::A(2, 2).in_final_location(&grotto));
+        grotto.active_amphis = vec![Amphipod::new('A', (1, 2)), Amphipod::new('A', (2, 2))]
+            .into_iter()
+            .collect();
+        assert!(Amphipod::new('A', (1, 2)).in_final_location(&grotto));
+        assert!(Amphipod::new('A', (2, 2)).in_final_location(&grotto));
 
-        grotto.active_amphis = vec![Amphipod::A(1, 2), Amphipod::B(2, 2)].into_iter().collect();
-        assert!(!Amphipod::A(1, 2).in_final_location(&grotto));
-        assert!(!Amphipod::B(2, 2).in_final_location(&grotto));
+        grotto.active_amphis = vec![Amphipod::new('A', (1, 2)), Amphipod::new('B', (2, 2))]
+            .into_iter()
+            .collect();
+        assert!(!Amphipod::new('A', (1, 2)).in_final_location(&grotto));
+        assert!(!Amphipod::new('B', (2, 2)).in_final_location(&grotto));
 
         // Bigger Examples
         grotto.map_depth = 4;
         grotto.active_amphis = vec![
-            Amphipod::A(1, 2),
-            Amphipod::B(2, 2),
-            Amphipod::A(3, 2),
-            Amphipod::A(4, 2),
+            Amphipod::new('A', (1, 2)),
+            Amphipod::new('B', (2, 2)),
+            Amphipod::new('A', (3, 2)),
+            Amphipod::new('A', (4, 2)),
         ]
         .into_iter()
         .collect();
-        assert!(!Amphipod::A(1, 2).in_final_location(&grotto));
-        assert!(!Amphipod::B(2, 2).in_final_location(&grotto));
-        assert!(Amphipod::A(3, 2).in_final_location(&grotto));
-        assert!(Amphipod::A(4, 2).in_final_location(&grotto));
+        assert!(!Amphipod::new('A', (1, 2)).in_final_location(&grotto));
+        assert!(!Amphipod::new('B', (2, 2)).in_final_location(&grotto));
+        assert!(Amphipod::new('A', (3, 2)).in_final_location(&grotto));
+        assert!(Amphipod::new('A', (4, 2)).in_final_location(&grotto));
     }
 }
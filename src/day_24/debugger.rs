@@ -0,0 +1,383 @@
+use super::{Computer, Instruction};
+use std::io::{stdin, stdout, BufRead, Write};
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Breakpoint {
+    /// Stop before running the instruction at this index.
+    Instruction(usize),
+    /// Stop before the `inp` that reads the digit at this index.
+    Input(usize),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Command {
+    Step(usize),
+    Continue,
+    Break(Breakpoint),
+    Delete(usize),
+    Breakpoints,
+    Print,
+    List,
+    Rewind(usize),
+    Input(Vec<i64>),
+    Restart,
+    Help,
+    Quit,
+}
+
+const HELP: &str = "\
+step [n]         run the next n instructions (default 1)
+continue         run until a breakpoint or the end of the program
+break <i>        stop before instruction i
+break inp <n>    stop before the inp that reads digit n
+delete <n>       remove breakpoint n
+breakpoints      list the breakpoints
+print            print the registers
+list             show the instructions around the current one
+rewind [n]       undo the last n instructions (default 1)
+input <digits>   restart with a new model number
+restart          go back to the start of the program
+quit             leave the debugger";
+
+impl TryFrom<&str> for Command {
+    type Error = String;
+
+    fn try_from(input: &str) -> Result<Self, Self::Error> {
+        let words: Vec<&str> = input.split_whitespace().collect();
+        let number = |word: Option<&&str>, default: Option<usize>| match word {
+            Some(word) => word.parse().map_err(|_| format!("{} isn't a number", word)),
+            None => default.ok_or_else(|| "That needs a number".to_string()),
+        };
+        let command = match words.as_slice() {
+            ["s" | "step", rest @ ..] => Command::Step(number(rest.first(), Some(1))?),
+            ["c" | "continue"] => Command::Continue,
+            ["b" | "break", "inp", rest @ ..] => Command::Break(Breakpoint::Input(number(rest.first(), None)?)),
+            ["b" | "break", rest @ ..] => Command::Break(Breakpoint::Instruction(number(rest.first(), None)?)),
+            ["d" | "delete", rest @ ..] => Command::Delete(number(rest.first(), None)?),
+            ["breakpoints"] => Command::Breakpoints,
+            ["p" | "print"] => Command::Print,
+            ["l" | "list"] => Command::List,
+            ["r" | "rewind", rest @ ..] => Command::Rewind(number(rest.first(), Some(1))?),
+            ["i" | "input", digits] => Command::Input(parse_digits(digits)?),
+            ["restart"] => Command::Restart,
+            ["h" | "help"] => Command::Help,
+            ["q" | "quit"] => Command::Quit,
+            _ => return Err(format!("You wot? Try help. ({})", input.trim())),
+        };
+        Ok(command)
+    }
+}
+
+pub fn parse_digits(input: &str) -> Result<Vec<i64>, String> {
+    input
+        .chars()
+        .map(|c| {
+            c.to_digit(10)
+                .map(|digit| digit as i64)
+                .ok_or_else(|| format!("{} isn't a digit", c))
+        })
+        .collect()
+}
+
+/// Why running stopped.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Stop {
+    Stepped,
+    Breakpoint(usize),
+    Finished,
+    NeedsInput,
+    Rejected,
+}
+
+/// Runs an ALU program one instruction at a time, remembering every previous state so it can be rewound.
+pub struct Debugger {
+    instructions: Vec<Instruction>,
+    inputs: Vec<i64>,
+    computer: Computer,
+    history: Vec<[i64; 4]>,
+    inputs_read: usize,
+    breakpoints: Vec<Breakpoint>,
+}
+
+#[allow(dead_code)]
+impl Debugger {
+    pub fn new(instructions: Vec<Instruction>, inputs: Vec<i64>) -> Self {
+        Debugger {
+            instructions,
+            inputs,
+            computer: Computer::new(),
+            history: vec![],
+            inputs_read: 0,
+            breakpoints: vec![],
+        }
+    }
+
+    /// The index of the next instruction to run.
+    pub fn position(&self) -> usize {
+        self.history.len()
+    }
+
+    pub fn registers(&self) -> [i64; 4] {
+        self.computer.registers()
+    }
+
+    pub fn inputs_read(&self) -> usize {
+        self.inputs_read
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        self.breakpoints.push(breakpoint);
+        self.breakpoints.len() - 1
+    }
+
+    pub fn remove_breakpoint(&mut self, index: usize) -> Option<Breakpoint> {
+        if index < self.breakpoints.len() {
+            Some(self.breakpoints.remove(index))
+        } else {
+            None
+        }
+    }
+
+    pub fn restart(&mut self, inputs: Vec<i64>) {
+        self.inputs = inputs;
+        self.computer = Computer::new();
+        self.history.clear();
+        self.inputs_read = 0;
+    }
+
+    pub fn step(&mut self) -> Stop {
+        let instruction = match self.instructions.get(self.position()) {
+            Some(instruction) => *instruction,
+            None => return Stop::Finished,
+        };
+        let is_input = matches!(instruction, Instruction::Inp(_));
+        if is_input && self.inputs_read == self.inputs.len() {
+            return Stop::NeedsInput;
+        }
+
+        let before = self.computer.registers();
+        let input = self.inputs[self.inputs_read..].iter().copied();
+        if self.computer.run_normal_instruction(instruction, input).is_err() {
+            self.computer = Computer::from_registers(before);
+            return Stop::Rejected;
+        }
+        self.history.push(before);
+        if is_input {
+            self.inputs_read += 1;
+        }
+        Stop::Stepped
+    }
+
+    /// Steps up to `count` times, stopping early at a breakpoint or anything that stops the program.
+    pub fn step_over(&mut self, count: usize) -> Stop {
+        for i in 0..count {
+            if i > 0 {
+                if let Some(hit) = self.breakpoint_hit() {
+                    return Stop::Breakpoint(hit);
+                }
+            }
+            let stop = self.step();
+            if stop != Stop::Stepped {
+                return stop;
+            }
+        }
+        Stop::Stepped
+    }
+
+    /// Runs until the next breakpoint, always making at least one step so it can get past the current one.
+    pub fn resume(&mut self) -> Stop {
+        self.step_over(usize::MAX)
+    }
+
+    pub fn rewind(&mut self, count: usize) -> usize {
+        let mut rewound = 0;
+        while rewound < count {
+            let registers = match self.history.pop() {
+                Some(registers) => registers,
+                None => break,
+            };
+            if let Some(Instruction::Inp(_)) = self.instructions.get(self.position()) {
+                self.inputs_read -= 1;
+            }
+            self.computer = Computer::from_registers(registers);
+            rewound += 1;
+        }
+        rewound
+    }
+
+    fn breakpoint_hit(&self) -> Option<usize> {
+        let position = self.position();
+        let at_input = matches!(self.instructions.get(position), Some(Instruction::Inp(_)));
+        self.breakpoints.iter().position(|breakpoint| match breakpoint {
+            Breakpoint::Instruction(index) => *index == position,
+            Breakpoint::Input(digit) => at_input && *digit == self.inputs_read,
+        })
+    }
+
+    fn describe(&self, stop: Stop) -> String {
+        let location = match self.instructions.get(self.position()) {
+            Some(instruction) => format!("[{}] {}", self.position(), instruction),
+            None => format!("[{}] end of program", self.position()),
+        };
+        let reason = match stop {
+            Stop::Stepped => String::new(),
+            Stop::Breakpoint(index) => format!("Hit breakpoint {} ", index),
+            Stop::Finished => "Finished ".to_string(),
+            Stop::NeedsInput => "Out of digits ".to_string(),
+            Stop::Rejected => "The ALU rejected this instruction ".to_string(),
+        };
+        format!("{}at {}\n{}", reason, location, self.computer)
+    }
+
+    fn listing(&self) -> String {
+        let start = self.position().saturating_sub(5);
+        let end = (self.position() + 6).min(self.instructions.len());
+        (start..end)
+            .map(|i| {
+                let marker = if i == self.position() { "=>" } else { "  " };
+                let stop = if self.breakpoints.contains(&Breakpoint::Instruction(i)) {
+                    "*"
+                } else {
+                    " "
+                };
+                format!("{}{} {:>4}  {}", marker, stop, i, self.instructions[i])
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Carries out a command, returning what to print, or `None` to quit.
+    pub fn execute(&mut self, command: Command) -> Option<String> {
+        let output = match command {
+            Command::Step(count) => {
+                let stop = self.step_over(count);
+                self.describe(stop)
+            }
+            Command::Continue => {
+                let stop = self.resume();
+                self.describe(stop)
+            }
+            Command::Break(breakpoint) => format!("Breakpoint {}: {:?}", self.add_breakpoint(breakpoint), breakpoint),
+            Command::Delete(index) => match self.remove_breakpoint(index) {
+                Some(breakpoint) => format!("Deleted {:?}", breakpoint),
+                None => format!("There's no breakpoint {}", index),
+            },
+            Command::Breakpoints => self
+                .breakpoints
+                .iter()
+                .enumerate()
+                .map(|(i, breakpoint)| format!("{}: {:?}", i, breakpoint))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Command::Print => format!("{} after {} digits", self.computer, self.inputs_read),
+            Command::List => self.listing(),
+            Command::Rewind(count) => {
+                let rewound = self.rewind(count);
+                format!("Rewound {}\n{}", rewound, self.describe(Stop::Stepped))
+            }
+            Command::Input(inputs) => {
+                self.restart(inputs);
+                self.describe(Stop::Stepped)
+            }
+            Command::Restart => {
+                self.restart(self.inputs.clone());
+                self.describe(Stop::Stepped)
+            }
+            Command::Help => HELP.to_string(),
+            Command::Quit => return None,
+        };
+        Some(output)
+    }
+}
+
+/// Reads commands from stdin until told to quit.
+pub fn run_repl(mut debugger: Debugger) {
+    println!("{}", debugger.describe(Stop::Stepped));
+    let stdin = stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(alu) ");
+        stdout().flush().unwrap();
+        let line = match lines.next() {
+            Some(Ok(line)) => line,
+            _ => return,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        match Command::try_from(line.as_str()) {
+            Ok(command) => match debugger.execute(command) {
+                Some(output) => println!("{}", output),
+                None => return,
+            },
+            Err(error) => println!("{}", error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse_from_str;
+    use super::*;
+
+    const PROGRAM: &str = "inp w\nadd z w\nmul z 3\ninp x\nadd z x\nmod z 5";
+
+    #[test]
+    fn test_step_and_rewind() {
+        let mut debugger = Debugger::new(parse_from_str(PROGRAM), vec![4, 2]);
+        assert_eq!(debugger.step_over(3), Stop::Stepped);
+        assert_eq!(debugger.registers(), [4, 0, 0, 12]);
+        assert_eq!(debugger.resume(), Stop::Finished);
+        assert_eq!(debugger.registers(), [4, 2, 0, 4]);
+        assert_eq!(debugger.rewind(3), 3);
+        assert_eq!(debugger.position(), 3);
+        assert_eq!(debugger.inputs_read(), 1);
+        assert_eq!(debugger.registers(), [4, 0, 0, 12]);
+        assert_eq!(debugger.rewind(10), 3);
+        assert_eq!(debugger.registers(), [0; 4]);
+    }
+
+    #[test]
+    fn test_breakpoints() {
+        let mut debugger = Debugger::new(parse_from_str(PROGRAM), vec![4, 2]);
+        debugger.add_breakpoint(Breakpoint::Input(1));
+        debugger.add_breakpoint(Breakpoint::Instruction(5));
+        assert_eq!(debugger.resume(), Stop::Breakpoint(0));
+        assert_eq!(debugger.position(), 3);
+        assert_eq!(debugger.resume(), Stop::Breakpoint(1));
+        assert_eq!(debugger.position(), 5);
+        assert_eq!(debugger.remove_breakpoint(0), Some(Breakpoint::Input(1)));
+        debugger.restart(vec![4, 2]);
+        assert_eq!(debugger.resume(), Stop::Breakpoint(0));
+        assert_eq!(debugger.position(), 5);
+    }
+
+    #[test]
+    fn test_stops() {
+        let mut debugger = Debugger::new(parse_from_str(PROGRAM), vec![4]);
+        assert_eq!(debugger.resume(), Stop::NeedsInput);
+        assert_eq!(debugger.position(), 3);
+        let mut debugger = Debugger::new(parse_from_str("inp w\nmod w x"), vec![4]);
+        assert_eq!(debugger.resume(), Stop::Rejected);
+        assert_eq!(debugger.position(), 1);
+        assert_eq!(debugger.registers(), [4, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_commands() {
+        assert_eq!(Command::try_from("step"), Ok(Command::Step(1)));
+        assert_eq!(Command::try_from("s 18"), Ok(Command::Step(18)));
+        assert_eq!(
+            Command::try_from("break inp 3"),
+            Ok(Command::Break(Breakpoint::Input(3)))
+        );
+        assert_eq!(
+            Command::try_from("b 40"),
+            Ok(Command::Break(Breakpoint::Instruction(40)))
+        );
+        assert_eq!(Command::try_from("input 1357"), Ok(Command::Input(vec![1, 3, 5, 7])));
+        assert!(Command::try_from("break").is_err());
+        assert!(Command::try_from("input 12a").is_err());
+        assert!(Command::try_from("frobnicate").is_err());
+    }
+}
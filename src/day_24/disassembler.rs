@@ -0,0 +1,216 @@
+use super::{digit_constraints, signed, Instruction, Rhs, RupInstruction, Variable};
+use std::fmt::{Display, Formatter};
+
+/// What each of the 18 instructions in a MONAD block has to be, where only a few of them vary.
+#[derive(Debug, Clone, Copy)]
+enum Slot {
+    Exact(Instruction),
+    Divide,
+    XNumber,
+    YNumber,
+}
+
+use Variable::{W, X, Y, Z};
+const TEMPLATE: [Slot; 18] = [
+    Slot::Exact(Instruction::Inp(W)),
+    Slot::Exact(Instruction::Mul(X, Rhs::Literal(0))),
+    Slot::Exact(Instruction::Add(X, Rhs::Variable(Z))),
+    Slot::Exact(Instruction::Mod(X, Rhs::Literal(26))),
+    Slot::Divide,
+    Slot::XNumber,
+    Slot::Exact(Instruction::Eql(X, Rhs::Variable(W))),
+    Slot::Exact(Instruction::Eql(X, Rhs::Literal(0))),
+    Slot::Exact(Instruction::Mul(Y, Rhs::Literal(0))),
+    Slot::Exact(Instruction::Add(Y, Rhs::Literal(25))),
+    Slot::Exact(Instruction::Mul(Y, Rhs::Variable(X))),
+    Slot::Exact(Instruction::Add(Y, Rhs::Literal(1))),
+    Slot::Exact(Instruction::Mul(Z, Rhs::Variable(Y))),
+    Slot::Exact(Instruction::Mul(Y, Rhs::Literal(0))),
+    Slot::Exact(Instruction::Add(Y, Rhs::Variable(W))),
+    Slot::YNumber,
+    Slot::Exact(Instruction::Mul(Y, Rhs::Variable(X))),
+    Slot::Exact(Instruction::Add(Z, Rhs::Variable(Y))),
+];
+
+impl Display for Slot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Slot::Exact(instruction) => write!(f, "`{}`", instruction),
+            Slot::Divide => write!(f, "`div z 1` or `div z 26`"),
+            Slot::XNumber => write!(f, "`add x <number>`"),
+            Slot::YNumber => write!(f, "`add y <number>`"),
+        }
+    }
+}
+
+/// Where a program stops looking like the MONAD.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct BlockMismatch {
+    pub block: usize,
+    pub index: usize,
+    expected: usize,
+    pub found: Option<Instruction>,
+}
+
+impl Display for BlockMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Block {} isn't MONAD shaped: instruction {} should be {}",
+            self.block, self.index, TEMPLATE[self.expected]
+        )?;
+        match self.found {
+            Some(instruction) => write!(f, " but is `{}`", instruction),
+            None => write!(f, " but the program has ended"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum BlockKind {
+    /// Always pushes `w + y_number` onto `z`, as `x_number` is too big to ever equal a digit.
+    Push,
+    /// Pops `z`, then pushes again unless the digit matches.
+    Pop,
+    /// Doesn't pop, but might not push either.
+    Conditional,
+}
+
+impl RupInstruction {
+    pub fn kind(&self) -> BlockKind {
+        if self.divide {
+            BlockKind::Pop
+        } else if self.x_number > 9 {
+            BlockKind::Push
+        } else {
+            BlockKind::Conditional
+        }
+    }
+}
+
+fn match_block(block: usize, instructions: &[Instruction]) -> Result<RupInstruction, BlockMismatch> {
+    let mut rup = RupInstruction {
+        x_number: 0,
+        y_number: 0,
+        divide: false,
+    };
+    for (expected, slot) in TEMPLATE.iter().enumerate() {
+        let index = block * TEMPLATE.len() + expected;
+        let found = instructions.get(index).copied();
+        let matched = match (slot, found) {
+            (Slot::Exact(instruction), Some(found)) => *instruction == found,
+            (Slot::Divide, Some(Instruction::Div(Z, Rhs::Literal(n)))) if n == 1 || n == 26 => {
+                rup.divide = n == 26;
+                true
+            }
+            (Slot::XNumber, Some(Instruction::Add(X, Rhs::Literal(n)))) => {
+                rup.x_number = n;
+                true
+            }
+            (Slot::YNumber, Some(Instruction::Add(Y, Rhs::Literal(n)))) => {
+                rup.y_number = n;
+                true
+            }
+            _ => false,
+        };
+        if !matched {
+            return Err(BlockMismatch {
+                block,
+                index,
+                expected,
+                found,
+            });
+        }
+    }
+    Ok(rup)
+}
+
+/// Matches every block of the program against the MONAD's, reporting the first instruction that doesn't fit.
+pub fn disassemble(instructions: &[Instruction]) -> Result<Vec<RupInstruction>, BlockMismatch> {
+    let blocks = instructions.chunks(TEMPLATE.len()).len();
+    (0..blocks).map(|block| match_block(block, instructions)).collect()
+}
+
+/// One line of pseudocode per digit, noting which digits the pops tie together.
+pub fn listing(rups: &[RupInstruction]) -> String {
+    let constraints = digit_constraints(rups).unwrap_or_default();
+    rups.iter()
+        .enumerate()
+        .map(|(i, rup)| {
+            let kind = match rup.kind() {
+                BlockKind::Push => "push",
+                BlockKind::Pop => "pop ",
+                BlockKind::Conditional => "??? ",
+            };
+            let mut line = format!("{:>2} {}  {}", i, kind, rup);
+            if let Some(constraint) = constraints.iter().find(|constraint| constraint.popped == i) {
+                line += &format!(
+                    "  // digit {} = digit {} {}",
+                    i,
+                    constraint.pushed,
+                    signed(constraint.offset)
+                );
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse_from_str;
+    use super::*;
+
+    const PROGRAM: &str = include_str!("../../inputs/day24.txt");
+
+    #[test]
+    fn test_disassemble_puzzle() {
+        let rups = disassemble(&parse_from_str(PROGRAM)).unwrap();
+        assert_eq!(rups.len(), 14);
+        assert_eq!(rups.iter().filter(|rup| rup.kind() == BlockKind::Push).count(), 7);
+        assert_eq!(rups.iter().filter(|rup| rup.kind() == BlockKind::Pop).count(), 7);
+        assert_eq!(
+            listing(&rups).lines().filter(|line| line.contains("// digit")).count(),
+            7
+        );
+    }
+
+    #[test]
+    fn test_mismatches() {
+        let mut instructions = parse_from_str(PROGRAM);
+        instructions[18 + 4] = Instruction::Div(Z, Rhs::Literal(13));
+        let mismatch = disassemble(&instructions).unwrap_err();
+        assert_eq!(mismatch.block, 1);
+        assert_eq!(mismatch.index, 22);
+        assert_eq!(
+            mismatch.to_string(),
+            "Block 1 isn't MONAD shaped: instruction 22 should be `div z 1` or `div z 26` but is `div z 13`"
+        );
+
+        let instructions = parse_from_str(PROGRAM);
+        let mismatch = disassemble(&instructions[..40]).unwrap_err();
+        assert_eq!(mismatch.block, 2);
+        assert_eq!(mismatch.found, None);
+        assert_eq!(
+            mismatch.to_string(),
+            "Block 2 isn't MONAD shaped: instruction 40 should be `div z 1` or `div z 26` but the program has ended"
+        );
+    }
+
+    #[test]
+    fn test_pseudocode() {
+        let push = RupInstruction {
+            x_number: 12,
+            y_number: 7,
+            divide: false,
+        };
+        let pop = RupInstruction {
+            x_number: -8,
+            y_number: -3,
+            divide: true,
+        };
+        assert_eq!(push.to_string(), "if z%26 + 12 != w { z = z*26 + w + 7 }");
+        assert_eq!(pop.to_string(), "x = z%26 - 8; z /= 26; if x != w { z = z*26 + w - 3 }");
+    }
+}
@@ -3,6 +3,13 @@ use std::fmt::{Display, Formatter};
 use std::fs::read_to_string;
 use std::path::Path;
 
+mod debugger;
+mod disassembler;
+mod optimiser;
+mod symbolic;
+
+use disassembler::disassemble;
+
 #[derive(Ord, PartialOrd, Eq, PartialEq, Debug, Clone, Copy)]
 enum Variable {
     W,
@@ -23,6 +30,18 @@ impl From<&str> for Variable {
     }
 }
 
+impl Display for Variable {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Variable::W => "w",
+            Variable::X => "x",
+            Variable::Y => "y",
+            Variable::Z => "z",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 #[derive(Ord, PartialOrd, Eq, PartialEq, Debug, Clone, Copy)]
 enum Rhs {
     Literal(i64),
@@ -38,6 +57,15 @@ impl From<&str> for Rhs {
     }
 }
 
+impl Display for Rhs {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rhs::Literal(n) => write!(f, "{}", n),
+            Rhs::Variable(variable) => write!(f, "{}", variable),
+        }
+    }
+}
+
 #[derive(Ord, PartialOrd, Eq, PartialEq, Debug, Clone, Copy)]
 enum Instruction {
     Inp(Variable),
@@ -48,6 +76,33 @@ enum Instruction {
     Eql(Variable, Rhs),
 }
 
+/// What `add`, `mul`, `div`, `mod` and `eql` do to their two values, under the puzzle's rules: `div` can't
+/// divide by 0, and `mod` needs a value that isn't negative and a divisor above 0. Anything too big for an
+/// `i64` is rejected too, rather than wrapping.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Operator {
+    Add,
+    Mul,
+    Div,
+    Mod,
+    Eql,
+}
+
+impl Operator {
+    fn apply(self, a: i64, b: i64) -> Option<i64> {
+        match self {
+            Operator::Add => a.checked_add(b),
+            Operator::Mul => a.checked_mul(b),
+            Operator::Div => a.checked_div(b),
+            Operator::Mod if a < 0 || b <= 0 => None,
+            Operator::Mod => Some(a % b),
+            Operator::Eql => Some((a == b) as i64),
+        }
+    }
+}
+
+/// The condensed form of one 18 instruction block of the MONAD, which reads a digit `w` and does
+/// `x = z % 26 + x_number`, then `z /= 26` if `divide`, then `z = z * 26 + w + y_number` if `x != w`.
 #[derive(Ord, PartialOrd, Eq, PartialEq, Debug, Clone, Copy)]
 struct RupInstruction {
     x_number: i64,
@@ -55,13 +110,23 @@ struct RupInstruction {
     divide: bool,
 }
 
+/// Writes a number to be added as `+ n` or `- n`.
+fn signed(n: i64) -> String {
+    if n < 0 {
+        format!("- {}", -n)
+    } else {
+        format!("+ {}", n)
+    }
+}
+
 impl Display for RupInstruction {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{{x: {}, y: {}, div: {}}}",
-            self.x_number, self.y_number, self.divide
-        )
+        let push = format!("{{ z = z*26 + w {} }}", signed(self.y_number));
+        if self.divide {
+            write!(f, "x = z%26 {}; z /= 26; if x != w {}", signed(self.x_number), push)
+        } else {
+            write!(f, "if z%26 {} != w {}", signed(self.x_number), push)
+        }
     }
 }
 
@@ -83,6 +148,19 @@ impl From<&str> for Instruction {
     }
 }
 
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instruction::Inp(variable) => write!(f, "inp {}", variable),
+            Instruction::Add(variable, rhs) => write!(f, "add {} {}", variable, rhs),
+            Instruction::Mul(variable, rhs) => write!(f, "mul {} {}", variable, rhs),
+            Instruction::Div(variable, rhs) => write!(f, "div {} {}", variable, rhs),
+            Instruction::Mod(variable, rhs) => write!(f, "mod {} {}", variable, rhs),
+            Instruction::Eql(variable, rhs) => write!(f, "eql {} {}", variable, rhs),
+        }
+    }
+}
+
 struct Computer {
     w: i64,
     x: i64,
@@ -102,6 +180,14 @@ impl Computer {
         Computer { w: 0, x: 0, y: 0, z: 0 }
     }
 
+    fn from_registers([w, x, y, z]: [i64; 4]) -> Self {
+        Computer { w, x, y, z }
+    }
+
+    fn registers(&self) -> [i64; 4] {
+        [self.w, self.x, self.y, self.z]
+    }
+
     fn run_normal(&mut self, instructions: &[Instruction], input: Vec<i64>) -> Option<i64> {
         let mut input = input.into_iter();
         for (_i, instruction) in instructions.iter().enumerate() {
@@ -133,56 +219,15 @@ impl Computer {
     where
         T: Iterator<Item = i64>,
     {
-        if variable != Variable::W {
-            panic!("Got an input that didn't go to w")
-        }
         let lhs = self.lhs_reference(variable);
         *lhs = input.next().unwrap();
         Ok(())
     }
 
-    fn add_instruction(&mut self, variable: Variable, rhs: Rhs) -> Result<(), ()> {
-        let rhs = self.deconstruct_rhs(rhs);
-        let lhs = self.lhs_reference(variable);
-        *lhs += rhs;
-        Ok(())
-    }
-
-    fn mul_instruction(&mut self, variable: Variable, rhs: Rhs) -> Result<(), ()> {
-        let rhs = self.deconstruct_rhs(rhs);
-        let lhs = self.lhs_reference(variable);
-        *lhs *= rhs;
-        Ok(())
-    }
-
-    fn div_instruction(&mut self, variable: Variable, rhs: Rhs) -> Result<(), ()> {
-        let rhs = self.deconstruct_rhs(rhs);
-        let lhs = self.lhs_reference(variable);
-        if rhs < 0 {
-            return Err(());
-        }
-        *lhs /= rhs;
-        Ok(())
-    }
-
-    fn mod_instruction(&mut self, variable: Variable, rhs: Rhs) -> Result<(), ()> {
+    fn operate(&mut self, variable: Variable, operator: Operator, rhs: Rhs) -> Result<(), ()> {
         let rhs = self.deconstruct_rhs(rhs);
         let lhs = self.lhs_reference(variable);
-        if rhs < 0 {
-            return Err(());
-        }
-        *lhs %= rhs;
-        Ok(())
-    }
-
-    fn eql_instruction(&mut self, variable: Variable, rhs: Rhs) -> Result<(), ()> {
-        let rhs = self.deconstruct_rhs(rhs);
-        let lhs = self.lhs_reference(variable);
-        if *lhs == rhs {
-            *lhs = 1;
-        } else {
-            *lhs = 0;
-        }
+        *lhs = operator.apply(*lhs, rhs).ok_or(())?;
         Ok(())
     }
 
@@ -192,11 +237,11 @@ impl Computer {
     {
         match instruction {
             Instruction::Inp(variable) => self.inp_instruction(variable, input),
-            Instruction::Add(variable, rhs) => self.add_instruction(variable, rhs),
-            Instruction::Mul(variable, rhs) => self.mul_instruction(variable, rhs),
-            Instruction::Div(variable, rhs) => self.div_instruction(variable, rhs),
-            Instruction::Mod(variable, rhs) => self.mod_instruction(variable, rhs),
-            Instruction::Eql(variable, rhs) => self.eql_instruction(variable, rhs),
+            Instruction::Add(variable, rhs) => self.operate(variable, Operator::Add, rhs),
+            Instruction::Mul(variable, rhs) => self.operate(variable, Operator::Mul, rhs),
+            Instruction::Div(variable, rhs) => self.operate(variable, Operator::Div, rhs),
+            Instruction::Mod(variable, rhs) => self.operate(variable, Operator::Mod, rhs),
+            Instruction::Eql(variable, rhs) => self.operate(variable, Operator::Eql, rhs),
         }
     }
 
@@ -236,17 +281,39 @@ impl Computer {
 
 type Input = Vec<Instruction>;
 pub fn solve_part_1() -> Result<(), ()> {
-    let input = parse_from_file("./inputs/day20.txt");
+    let input = parse_from_file("./inputs/day24.txt");
     println!("Solution: {}", part_one(input));
     Ok(())
 }
 
 pub fn solve_part_2() -> Result<(), ()> {
-    let input = parse_from_file("./inputs/day20.txt");
+    let input = parse_from_file("./inputs/day24.txt");
     println!("Solution: {}", part_two(input));
     Ok(())
 }
 
+/// Loads the puzzle input into the interactive debugger, optionally with a model number to feed it.
+pub fn debug(digits: Option<String>) -> Result<(), ()> {
+    let inputs = match digits {
+        Some(digits) => debugger::parse_digits(&digits).map_err(|error| println!("{}", error))?,
+        None => vec![],
+    };
+    debugger::run_repl(debugger::Debugger::new(parse_from_file("./inputs/day24.txt"), inputs));
+    Ok(())
+}
+
+/// Prints the puzzle input as pseudocode, one MONAD block per digit.
+pub fn print_disassembly() -> Result<(), ()> {
+    match disassemble(&parse_from_file("./inputs/day24.txt")) {
+        Ok(rups) => println!("{}", disassembler::listing(&rups)),
+        Err(mismatch) => {
+            println!("{}", mismatch);
+            return Err(());
+        }
+    }
+    Ok(())
+}
+
 fn parse_from_file<T: AsRef<Path>>(filename: T) -> Input {
     let input = read_to_string(filename).unwrap();
     parse_from_str(&input)
@@ -258,61 +325,236 @@ fn parse_from_str(input: &str) -> Input {
 
 #[allow(dead_code)]
 fn parse_from_str_to_rups(input: &str) -> Vec<RupInstruction> {
-    input
-        .lines()
-        .collect::<Vec<_>>()
-        .chunks(18)
-        .map(|chunk| -> RupInstruction {
-            let div_ins = chunk[4];
-            let x_ins = chunk[5];
-            let y_ins = chunk[15];
-            let divide: bool = match Instruction::from(div_ins) {
-                Instruction::Div(Variable::Z, Rhs::Literal(26)) => true,
-                Instruction::Div(Variable::Z, Rhs::Literal(1)) => false,
-                _ => panic!("You wot? {:?}", Instruction::from(div_ins)),
-            };
-            let x_number = if let Instruction::Add(Variable::X, Rhs::Literal(n)) = Instruction::from(x_ins) {
-                n
-            } else {
-                panic!("You wot? {:?}", Instruction::from(x_ins))
-            };
-            let y_number = if let Instruction::Add(Variable::Y, Rhs::Literal(n)) = Instruction::from(y_ins) {
-                n
-            } else {
-                panic!("You wot? {:?}", Instruction::from(y_ins))
-            };
+    disassemble(&parse_from_str(input)).unwrap_or_else(|mismatch| panic!("You wot? {}", mismatch))
+}
 
-            RupInstruction {
-                x_number,
-                y_number,
-                divide,
-            }
-        })
-        .collect()
+/// Two digits of the model number tied together by the MONAD: digit `popped` has to equal digit `pushed`
+/// plus `offset` for `z` to end up back where it was before `pushed` was read.
+#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+struct DigitConstraint {
+    pushed: usize,
+    popped: usize,
+    offset: i64,
 }
 
-fn part_one(_: Input) -> usize {
-    unimplemented!()
+/// Treats `z` as a stack of base 26 digits. A block that doesn't divide always pushes `w + y_number`,
+/// because its `x_number` is too big to ever equal a digit. A block that divides pops that value back off,
+/// and only avoids pushing again if its digit equals the popped value plus its `x_number`. As every block
+/// that divides has to avoid pushing for `z` to finish at 0, the blocks pair up into constraints.
+fn digit_constraints(rups: &[RupInstruction]) -> Option<Vec<DigitConstraint>> {
+    let mut stack: Vec<(usize, i64)> = vec![];
+    let mut constraints = vec![];
+    for (i, rup) in rups.iter().enumerate() {
+        if rup.divide {
+            let (pushed, y_number) = stack.pop()?;
+            constraints.push(DigitConstraint {
+                pushed,
+                popped: i,
+                offset: y_number + rup.x_number,
+            });
+        } else if rup.x_number > 9 {
+            stack.push((i, rup.y_number));
+        } else {
+            return None;
+        }
+    }
+    if stack.is_empty() {
+        Some(constraints)
+    } else {
+        None
+    }
+}
+
+fn digits_from_constraints(rups: &[RupInstruction], largest: bool) -> Option<Vec<i64>> {
+    let mut digits = vec![0; rups.len()];
+    for DigitConstraint { pushed, popped, offset } in digit_constraints(rups)? {
+        let pushed_digit = if largest { 9.min(9 - offset) } else { 1.max(1 - offset) };
+        if !(1..=9).contains(&pushed_digit) || !(1..=9).contains(&(pushed_digit + offset)) {
+            return None;
+        }
+        digits[pushed] = pushed_digit;
+        digits[popped] = pushed_digit + offset;
+    }
+    Some(digits)
+}
+
+/// Finds the largest (or smallest) model number that the program accepts, checking it with the full ALU.
+/// MONAD shaped programs are solved from their digit constraints, and anything else, or any MONAD whose
+/// constraints don't give a number the ALU accepts, symbolically.
+fn find_model_number(instructions: &[Instruction], largest: bool) -> Option<usize> {
+    let accepted = |digits: &Vec<i64>| Computer::new().run_normal(instructions, digits.clone()) == Some(0);
+    let digits = disassemble(instructions)
+        .ok()
+        .and_then(|rups| digits_from_constraints(&rups, largest))
+        .filter(accepted)
+        .or_else(|| symbolic::solve(instructions, largest).filter(accepted))?;
+    Some(digits.into_iter().fold(0, |acc, digit| acc * 10 + digit as usize))
 }
 
-fn part_two(_: Input) -> usize {
-    unimplemented!();
+fn part_one(input: Input) -> usize {
+    find_model_number(&input, true).expect("No model number is valid")
+}
+
+fn part_two(input: Input) -> usize {
+    find_model_number(&input, false).expect("No model number is valid")
 }
 
 #[cfg(test)]
 mod tests {
+    use super::optimiser::{optimise, CompiledProgram, Context};
     use super::*;
     use rand::Rng;
     const EXAMPLE_PROGRAM: &str = include_str!("../../inputs/day24.txt");
 
+    fn monad_block(divide: bool, x_number: i64, y_number: i64) -> String {
+        format!(
+            "inp w\nmul x 0\nadd x z\nmod x 26\ndiv z {}\nadd x {}\neql x w\neql x 0\nmul y 0\nadd y 25\n\
+             mul y x\nadd y 1\nmul z y\nmul y 0\nadd y w\nadd y {}\nmul y x\nadd z y\n",
+            if divide { 26 } else { 1 },
+            x_number,
+            y_number
+        )
+    }
+
+    fn model_number_digits(model_number: usize) -> Vec<i64> {
+        model_number
+            .to_string()
+            .chars()
+            .map(|digit| digit.to_digit(10).unwrap() as i64)
+            .collect()
+    }
+
     #[test]
     fn test_part_one() {
-        unimplemented!();
+        let instructions = parse_from_str(EXAMPLE_PROGRAM);
+        let digits = model_number_digits(part_one(instructions.clone()));
+        assert_eq!(digits.len(), 14);
+        assert_eq!(Computer::new().run_normal(&instructions, digits), Some(0));
     }
 
     #[test]
     fn test_part_two() {
-        unimplemented!();
+        let instructions = parse_from_str(EXAMPLE_PROGRAM);
+        let digits = model_number_digits(part_two(instructions.clone()));
+        assert_eq!(digits.len(), 14);
+        assert_eq!(Computer::new().run_normal(&instructions, digits), Some(0));
+        assert!(part_two(instructions.clone()) <= part_one(instructions));
+    }
+
+    #[test]
+    fn test_digit_constraints() {
+        let rups = vec![
+            RupInstruction {
+                x_number: 11,
+                y_number: 3,
+                divide: false,
+            },
+            RupInstruction {
+                x_number: 14,
+                y_number: 5,
+                divide: false,
+            },
+            RupInstruction {
+                x_number: -7,
+                y_number: 2,
+                divide: true,
+            },
+            RupInstruction {
+                x_number: -1,
+                y_number: 8,
+                divide: true,
+            },
+        ];
+        assert_eq!(
+            digit_constraints(&rups),
+            Some(vec![
+                DigitConstraint {
+                    pushed: 1,
+                    popped: 2,
+                    offset: -2
+                },
+                DigitConstraint {
+                    pushed: 0,
+                    popped: 3,
+                    offset: 2
+                },
+            ])
+        );
+        assert_eq!(digit_constraints(&rups[..3]), None);
+        assert_eq!(digit_constraints(&rups[2..]), None);
+    }
+
+    #[test]
+    fn test_model_numbers_against_brute_force() {
+        let program = [(false, 11, 3), (false, 14, 5), (true, -7, 2), (true, -1, 8)]
+            .iter()
+            .map(|(divide, x_number, y_number)| monad_block(*divide, *x_number, *y_number))
+            .collect::<String>();
+        let instructions = parse_from_str(&program);
+        let compiled = CompiledProgram::compile(&optimise(&instructions, Context::whole_program()));
+        let accepted: Vec<usize> = (1111..=9999)
+            .filter(|model_number| {
+                let digits = model_number_digits(*model_number);
+                if digits.contains(&0) {
+                    return false;
+                }
+                let z = Computer::new().run_normal(&instructions, digits.clone());
+                assert_eq!(compiled.run(&digits), z);
+                z == Some(0)
+            })
+            .collect();
+        assert_eq!(find_model_number(&instructions, true), accepted.last().copied());
+        assert_eq!(find_model_number(&instructions, false), accepted.first().copied());
+        assert_eq!(find_model_number(&instructions, true), Some(7979));
+        assert_eq!(find_model_number(&instructions, false), Some(1313));
+    }
+
+    #[test]
+    fn test_falls_back_to_symbolic() {
+        // The first block only avoids pushing when its digit is 1, so the blocks don't pair up.
+        let program = [(false, 1, 0), (false, 11, 3), (true, -2, 0)]
+            .iter()
+            .map(|(divide, x_number, y_number)| monad_block(*divide, *x_number, *y_number))
+            .collect::<String>();
+        let instructions = parse_from_str(&program);
+        assert!(digit_constraints(&disassemble(&instructions).unwrap()).is_none());
+        assert_eq!(find_model_number(&instructions, true), Some(189));
+        assert_eq!(find_model_number(&instructions, false), Some(112));
+    }
+
+    #[test]
+    fn test_engines_share_the_alu_rules() {
+        for (program, input, expected) in [
+            ("inp z\ndiv z -2", 7, Some(-3)),
+            ("inp z\ndiv z 0", 7, None),
+            ("inp z\nmod z -2", 7, None),
+            ("inp z\nadd z -9\nmod z 2", 2, None),
+            ("inp z\nadd z -9\ndiv z 2", 2, Some(-3)),
+            ("inp z\nmul z 1000000000000\nmul z z", 9, None),
+            ("inp z\nadd z 9223372036854775800", 9, None),
+        ] {
+            let instructions = parse_from_str(program);
+            let ops: Vec<_> = instructions.iter().copied().map(optimiser::Op::from).collect();
+            let mut symbolic = symbolic::SymbolicComputer::new();
+            symbolic.run(&instructions);
+            assert_eq!(Computer::new().run_normal(&instructions, vec![input]), expected);
+            assert_eq!(CompiledProgram::compile(&ops).run(&[input]), expected);
+            assert_eq!(symbolic.z.evaluate(&[input]), expected);
+        }
+    }
+
+    #[test]
+    fn test_symbolic_solve_agrees_with_constraints() {
+        let instructions = parse_from_str(EXAMPLE_PROGRAM);
+        let rups = disassemble(&instructions).unwrap();
+        assert_eq!(
+            symbolic::solve(&instructions, true),
+            digits_from_constraints(&rups, true)
+        );
+        assert_eq!(
+            symbolic::solve(&instructions, false),
+            digits_from_constraints(&rups, false)
+        );
     }
 
     #[test]
@@ -0,0 +1,370 @@
+use super::{Instruction, Operator, Rhs, Variable};
+use std::slice::Iter;
+
+/// The instructions the optimiser works with: the ALU's own plus a few that its passes reduce them to.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Op {
+    Inp(Variable),
+    Set(Variable, i64),
+    Copy(Variable, Variable),
+    Add(Variable, Rhs),
+    Mul(Variable, Rhs),
+    Div(Variable, Rhs),
+    Mod(Variable, Rhs),
+    Eql(Variable, Rhs),
+    Neq(Variable, Rhs),
+}
+
+impl From<Instruction> for Op {
+    fn from(instruction: Instruction) -> Self {
+        match instruction {
+            Instruction::Inp(variable) => Op::Inp(variable),
+            Instruction::Add(variable, rhs) => Op::Add(variable, rhs),
+            Instruction::Mul(variable, rhs) => Op::Mul(variable, rhs),
+            Instruction::Div(variable, rhs) => Op::Div(variable, rhs),
+            Instruction::Mod(variable, rhs) => Op::Mod(variable, rhs),
+            Instruction::Eql(variable, rhs) => Op::Eql(variable, rhs),
+        }
+    }
+}
+
+fn index(variable: Variable) -> usize {
+    match variable {
+        Variable::W => 0,
+        Variable::X => 1,
+        Variable::Y => 2,
+        Variable::Z => 3,
+    }
+}
+
+impl Op {
+    fn target(&self) -> Variable {
+        match *self {
+            Op::Inp(variable)
+            | Op::Set(variable, _)
+            | Op::Copy(variable, _)
+            | Op::Add(variable, _)
+            | Op::Mul(variable, _)
+            | Op::Div(variable, _)
+            | Op::Mod(variable, _)
+            | Op::Eql(variable, _)
+            | Op::Neq(variable, _) => variable,
+        }
+    }
+
+    fn reads(&self) -> Vec<Variable> {
+        match *self {
+            Op::Inp(_) | Op::Set(_, _) => vec![],
+            Op::Copy(_, from) => vec![from],
+            Op::Add(variable, rhs)
+            | Op::Mul(variable, rhs)
+            | Op::Div(variable, rhs)
+            | Op::Mod(variable, rhs)
+            | Op::Eql(variable, rhs)
+            | Op::Neq(variable, rhs) => match rhs {
+                Rhs::Literal(_) => vec![variable],
+                Rhs::Variable(other) => vec![variable, other],
+            },
+        }
+    }
+
+    fn with_rhs(&self, rhs: Rhs) -> Op {
+        match *self {
+            Op::Add(variable, _) => Op::Add(variable, rhs),
+            Op::Mul(variable, _) => Op::Mul(variable, rhs),
+            Op::Div(variable, _) => Op::Div(variable, rhs),
+            Op::Mod(variable, _) => Op::Mod(variable, rhs),
+            Op::Eql(variable, _) => Op::Eql(variable, rhs),
+            Op::Neq(variable, _) => Op::Neq(variable, rhs),
+            op => op,
+        }
+    }
+
+    fn rhs(&self) -> Option<Rhs> {
+        match *self {
+            Op::Add(_, rhs)
+            | Op::Mul(_, rhs)
+            | Op::Div(_, rhs)
+            | Op::Mod(_, rhs)
+            | Op::Eql(_, rhs)
+            | Op::Neq(_, rhs) => Some(rhs),
+            _ => None,
+        }
+    }
+
+    /// The function of the target and right hand side values that the op computes, or `None` for the ALU to
+    /// reject the program, exactly as `Computer` does.
+    fn operation(&self) -> Option<fn(i64, i64) -> Option<i64>> {
+        let operation: fn(i64, i64) -> Option<i64> = match self {
+            Op::Add(_, _) => |a, b| Operator::Add.apply(a, b),
+            Op::Mul(_, _) => |a, b| Operator::Mul.apply(a, b),
+            Op::Div(_, _) => |a, b| Operator::Div.apply(a, b),
+            Op::Mod(_, _) => |a, b| Operator::Mod.apply(a, b),
+            Op::Eql(_, _) => |a, b| Operator::Eql.apply(a, b),
+            Op::Neq(_, _) => |a, b| Operator::Eql.apply(a, b).map(|equal| 1 - equal),
+            _ => return None,
+        };
+        Some(operation)
+    }
+}
+
+/// What the optimiser is allowed to assume about the registers either side of the instructions.
+#[derive(Debug, Clone, Copy)]
+pub struct Context {
+    pub start: [Option<i64>; 4],
+    pub live_out: [bool; 4],
+}
+
+impl Context {
+    /// A whole program starts with every register at 0, and only `z` matters at the end.
+    #[allow(dead_code)]
+    pub fn whole_program() -> Context {
+        Context {
+            start: [Some(0); 4],
+            live_out: [false, false, false, true],
+        }
+    }
+
+    /// A piece of a program could start with anything in the registers, and all of them matter afterwards.
+    pub fn fragment() -> Context {
+        Context {
+            start: [None; 4],
+            live_out: [true; 4],
+        }
+    }
+}
+
+/// Runs every pass until the program stops shrinking. This assumes the program is valid for the inputs it
+/// will be given, as an instruction that could only have been rejected by the ALU may be removed.
+pub fn optimise(instructions: &[Instruction], context: Context) -> Vec<Op> {
+    let mut ops: Vec<Op> = instructions.iter().copied().map(Op::from).collect();
+    loop {
+        let before = ops.len();
+        ops = propagate_constants(&ops, context.start);
+        ops = reduce_strength(&ops);
+        ops = eliminate_dead_stores(&ops, context.live_out);
+        if ops.len() == before {
+            return ops;
+        }
+    }
+}
+
+/// Tracks which registers hold known values, substituting them in and folding any ops on them.
+pub fn propagate_constants(ops: &[Op], start: [Option<i64>; 4]) -> Vec<Op> {
+    let mut known = start;
+    let mut result = vec![];
+    for op in ops {
+        let mut op = *op;
+        if let Some(Rhs::Variable(other)) = op.rhs() {
+            if let Some(value) = known[index(other)] {
+                op = op.with_rhs(Rhs::Literal(value));
+            }
+        }
+        op = match (op, known[index(op.target())]) {
+            (Op::Copy(variable, from), _) => known[index(from)].map_or(op, |value| Op::Set(variable, value)),
+            (Op::Add(variable, Rhs::Variable(from)), Some(0)) | (Op::Mul(variable, Rhs::Variable(from)), Some(1)) => {
+                Op::Copy(variable, from)
+            }
+            _ => op,
+        };
+
+        let target = index(op.target());
+        if let (Some(operation), Some(Rhs::Literal(b)), Some(a)) = (op.operation(), op.rhs(), known[target]) {
+            if let Some(value) = operation(a, b) {
+                op = Op::Set(op.target(), value);
+            }
+        }
+
+        known[target] = match op {
+            Op::Set(_, value) if known[target] == Some(value) => continue,
+            Op::Set(_, value) => Some(value),
+            Op::Copy(_, from) => known[index(from)],
+            Op::Mul(_, _) if known[target] == Some(0) => continue,
+            Op::Mul(_, Rhs::Literal(0)) => {
+                op = Op::Set(op.target(), 0);
+                Some(0)
+            }
+            _ => None,
+        };
+        result.push(op);
+    }
+    result
+}
+
+/// Replaces ops with cheaper ones that do the same thing, and drops the ones that do nothing.
+pub fn reduce_strength(ops: &[Op]) -> Vec<Op> {
+    let mut result: Vec<Op> = vec![];
+    for op in ops {
+        let reduced = match *op {
+            Op::Add(_, Rhs::Literal(0)) | Op::Mul(_, Rhs::Literal(1)) | Op::Div(_, Rhs::Literal(1)) => None,
+            Op::Mul(variable, Rhs::Literal(0)) | Op::Mod(variable, Rhs::Literal(1)) => Some(Op::Set(variable, 0)),
+            Op::Eql(variable, Rhs::Variable(other)) if variable == other => Some(Op::Set(variable, 1)),
+            Op::Eql(variable, Rhs::Literal(0)) => match result.last() {
+                Some(Op::Eql(previous, rhs)) if *previous == variable => {
+                    let rhs = *rhs;
+                    result.pop();
+                    Some(Op::Neq(variable, rhs))
+                }
+                Some(Op::Neq(previous, rhs)) if *previous == variable => {
+                    let rhs = *rhs;
+                    result.pop();
+                    Some(Op::Eql(variable, rhs))
+                }
+                _ => Some(*op),
+            },
+            _ => Some(*op),
+        };
+        result.extend(reduced);
+    }
+    result
+}
+
+/// Works backwards from the registers that matter at the end, dropping any op whose result is never read.
+/// `inp` is always kept so that the remaining inputs still go to the right places.
+pub fn eliminate_dead_stores(ops: &[Op], live_out: [bool; 4]) -> Vec<Op> {
+    let mut live = live_out;
+    let mut result = vec![];
+    for op in ops.iter().rev() {
+        let target = index(op.target());
+        if !live[target] && !matches!(op, Op::Inp(_)) {
+            continue;
+        }
+        live[target] = false;
+        for read in op.reads() {
+            live[index(read)] = true;
+        }
+        result.push(*op);
+    }
+    result.reverse();
+    result
+}
+
+type Step = Box<dyn Fn(&mut [i64; 4], &mut Iter<i64>) -> Option<()>>;
+
+/// A program compiled down to a chain of closures, each of which has its registers and literals baked in.
+pub struct CompiledProgram {
+    steps: Vec<Step>,
+}
+
+#[allow(dead_code)]
+impl CompiledProgram {
+    pub fn compile(ops: &[Op]) -> CompiledProgram {
+        CompiledProgram {
+            steps: ops.iter().map(compile_op).collect(),
+        }
+    }
+
+    /// Runs from the given registers, returning them at the end, or `None` if the ALU would have rejected the
+    /// inputs or there weren't enough of them.
+    pub fn run_from(&self, mut registers: [i64; 4], inputs: &[i64]) -> Option<[i64; 4]> {
+        let mut inputs = inputs.iter();
+        for step in &self.steps {
+            step(&mut registers, &mut inputs)?;
+        }
+        Some(registers)
+    }
+
+    pub fn run(&self, inputs: &[i64]) -> Option<i64> {
+        self.run_from([0; 4], inputs).map(|registers| registers[3])
+    }
+}
+
+fn compile_op(op: &Op) -> Step {
+    let target = index(op.target());
+    match (*op, op.operation(), op.rhs()) {
+        (Op::Inp(_), _, _) => Box::new(move |registers, inputs| {
+            registers[target] = *inputs.next()?;
+            Some(())
+        }),
+        (Op::Set(_, value), _, _) => Box::new(move |registers, _| {
+            registers[target] = value;
+            Some(())
+        }),
+        (Op::Copy(_, from), _, _) => {
+            let from = index(from);
+            Box::new(move |registers, _| {
+                registers[target] = registers[from];
+                Some(())
+            })
+        }
+        (_, Some(operation), Some(Rhs::Literal(n))) => Box::new(move |registers, _| {
+            registers[target] = operation(registers[target], n)?;
+            Some(())
+        }),
+        (_, Some(operation), Some(Rhs::Variable(other))) => {
+            let other = index(other);
+            Box::new(move |registers, _| {
+                registers[target] = operation(registers[target], registers[other])?;
+                Some(())
+            })
+        }
+        _ => unreachable!("Every op with a right hand side has an operation"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{parse_from_str, Computer};
+    use super::*;
+    use rand::Rng;
+
+    const MONAD_BLOCK: &str = "inp w\nmul x 0\nadd x z\nmod x 26\ndiv z 1\nadd x 12\neql x w\neql x 0\nmul y 0\n\
+                               add y 25\nmul y x\nadd y 1\nmul z y\nmul y 0\nadd y w\nadd y 7\nmul y x\nadd z y";
+
+    #[test]
+    fn test_first_monad_block_folds_away() {
+        let ops = optimise(&parse_from_str(MONAD_BLOCK), Context::whole_program());
+        assert_eq!(
+            ops,
+            vec![
+                Op::Inp(Variable::W),
+                Op::Set(Variable::X, 12),
+                Op::Neq(Variable::X, Rhs::Variable(Variable::W)),
+                Op::Copy(Variable::Y, Variable::W),
+                Op::Add(Variable::Y, Rhs::Literal(7)),
+                Op::Mul(Variable::Y, Rhs::Variable(Variable::X)),
+                Op::Copy(Variable::Z, Variable::Y),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fragment_keeps_registers() {
+        let ops = optimise(&parse_from_str(MONAD_BLOCK), Context::fragment());
+        assert!(ops.contains(&Op::Copy(Variable::X, Variable::Z)));
+        assert!(!ops.contains(&Op::Set(Variable::X, 0)));
+        assert!(ops.contains(&Op::Neq(Variable::X, Rhs::Variable(Variable::W))));
+        assert!(!ops.contains(&Op::Div(Variable::Z, Rhs::Literal(1))));
+        let program = CompiledProgram::compile(&ops);
+        let mut computer = Computer::from_registers([3, 4, 5, 100]);
+        computer.run_normal(&parse_from_str(MONAD_BLOCK), vec![6]);
+        assert_eq!(program.run_from([3, 4, 5, 100], &[6]), Some(computer.registers()));
+    }
+
+    #[test]
+    fn test_dead_stores() {
+        let ops: Vec<Op> = parse_from_str("inp w\nadd x w\nmul x 0\nadd y 3\nadd z w")
+            .into_iter()
+            .map(Op::from)
+            .collect();
+        assert_eq!(
+            eliminate_dead_stores(&ops, [false, false, false, true]),
+            vec![Op::Inp(Variable::W), Op::Add(Variable::Z, Rhs::Variable(Variable::W))]
+        );
+    }
+
+    #[test]
+    fn test_compiled_matches_computer() {
+        let instructions = parse_from_str(include_str!("../../inputs/day24.txt"));
+        let compiled = CompiledProgram::compile(&optimise(&instructions, Context::whole_program()));
+        let unoptimised = CompiledProgram::compile(&instructions.iter().copied().map(Op::from).collect::<Vec<_>>());
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let input: Vec<i64> = (0..14).map(|_| rng.gen_range(1..=9)).collect();
+            let expected = Computer::new().run_normal(&instructions, input.clone());
+            assert_eq!(compiled.run(&input), expected);
+            assert_eq!(unoptimised.run(&input), expected);
+        }
+        assert_eq!(compiled.run(&[1; 13]), None);
+    }
+}
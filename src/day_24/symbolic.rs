@@ -0,0 +1,469 @@
+use super::optimiser::{optimise, CompiledProgram, Context};
+use super::{Instruction, Operator, Rhs, Variable};
+use hashbrown::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+/// The value of a register in terms of the digits read by `inp`. Anything that can be worked out is folded
+/// down as the tree is built, and every node knows the range of values it could take.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Expression {
+    Literal(i64),
+    Input(usize),
+    Operation {
+        operator: Operator,
+        lhs: Rc<Expression>,
+        rhs: Rc<Expression>,
+        range: (i64, i64),
+    },
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::Literal(n) => write!(f, "{}", n),
+            Expression::Input(i) => write!(f, "in[{}]", i),
+            Expression::Operation { operator, lhs, rhs, .. } => {
+                let symbol = match operator {
+                    Operator::Add => "+",
+                    Operator::Mul => "*",
+                    Operator::Div => "/",
+                    Operator::Mod => "%",
+                    Operator::Eql => "==",
+                };
+                write!(f, "({} {} {})", lhs, symbol, rhs)
+            }
+        }
+    }
+}
+
+const FULL_RANGE: (i64, i64) = (i64::MIN, i64::MAX);
+
+#[allow(dead_code)]
+impl Expression {
+    pub fn range(&self) -> (i64, i64) {
+        match self {
+            Expression::Literal(n) => (*n, *n),
+            Expression::Input(_) => (1, 9),
+            Expression::Operation { range, .. } => *range,
+        }
+    }
+
+    pub fn literal(&self) -> Option<i64> {
+        match self {
+            Expression::Literal(n) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn could_be(&self, value: i64) -> bool {
+        let (min, max) = self.range();
+        (min..=max).contains(&value)
+    }
+
+    /// The inputs this expression depends on.
+    pub fn inputs(&self) -> HashSet<usize> {
+        let mut inputs = HashSet::new();
+        let mut visited: HashSet<*const Expression> = HashSet::new();
+        let mut to_visit = vec![self];
+        while let Some(expression) = to_visit.pop() {
+            if !visited.insert(expression) {
+                continue;
+            }
+            match expression {
+                Expression::Literal(_) => (),
+                Expression::Input(i) => {
+                    inputs.insert(*i);
+                }
+                Expression::Operation { lhs, rhs, .. } => {
+                    to_visit.push(lhs);
+                    to_visit.push(rhs);
+                }
+            }
+        }
+        inputs
+    }
+
+    /// Works the expression out for concrete inputs, or returns `None` if the ALU would reject them.
+    pub fn evaluate(&self, inputs: &[i64]) -> Option<i64> {
+        self.evaluate_shared(inputs, &mut HashMap::new())
+    }
+
+    /// Registers refer back to each other's old values a lot, so each shared node is only evaluated once.
+    fn evaluate_shared(&self, inputs: &[i64], known: &mut HashMap<*const Expression, Option<i64>>) -> Option<i64> {
+        if let Some(value) = known.get(&(self as *const Expression)) {
+            return *value;
+        }
+        let value = match self {
+            Expression::Literal(n) => Some(*n),
+            Expression::Input(i) => inputs.get(*i).copied(),
+            Expression::Operation { operator, lhs, rhs, .. } => lhs
+                .evaluate_shared(inputs, known)
+                .zip(rhs.evaluate_shared(inputs, known))
+                .and_then(|(lhs, rhs)| operator.apply(lhs, rhs)),
+        };
+        known.insert(self, value);
+        value
+    }
+
+    /// If this is `a * m + b` where `b` is always in `0..m` and `a * m` is never negative, returns `a` and `b`.
+    fn split_multiple_of(&self, m: i64) -> Option<(&Rc<Expression>, &Rc<Expression>)> {
+        if let Expression::Operation {
+            operator: Operator::Add,
+            lhs,
+            rhs,
+            ..
+        } = self
+        {
+            for (multiple, remainder) in [(lhs, rhs), (rhs, lhs)] {
+                if let Expression::Operation {
+                    operator: Operator::Mul,
+                    lhs: a,
+                    rhs: factor,
+                    ..
+                } = multiple.as_ref()
+                {
+                    let (min, max) = remainder.range();
+                    if factor.literal() == Some(m) && a.range().0 >= 0 && min >= 0 && max < m {
+                        return Some((a, remainder));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+fn literal(n: i64) -> Rc<Expression> {
+    Rc::new(Expression::Literal(n))
+}
+
+/// The values the operation could give. Anything that might be rejected gets the full range, so only an
+/// operation that always succeeds can be narrowed down to a literal.
+fn range_of(operator: Operator, (a_min, a_max): (i64, i64), (b_min, b_max): (i64, i64)) -> (i64, i64) {
+    let corners = |combine: fn(i64, i64) -> Option<i64>| {
+        [
+            combine(a_min, b_min),
+            combine(a_min, b_max),
+            combine(a_max, b_min),
+            combine(a_max, b_max),
+        ]
+        .into_iter()
+        .collect::<Option<Vec<i64>>>()
+        .map_or(FULL_RANGE, |values| {
+            (*values.iter().min().unwrap(), *values.iter().max().unwrap())
+        })
+    };
+    match operator {
+        Operator::Add => corners(i64::checked_add),
+        Operator::Mul => corners(i64::checked_mul),
+        Operator::Div if b_min > 0 || b_max < 0 => corners(i64::checked_div),
+        Operator::Div => FULL_RANGE,
+        Operator::Mod if a_min >= 0 && b_min > 0 => (0, a_max.min(b_max - 1)),
+        Operator::Mod => FULL_RANGE,
+        Operator::Eql => (0, 1),
+    }
+}
+
+/// Combines two expressions, folding the result down as far as possible.
+pub fn operate(operator: Operator, lhs: Rc<Expression>, rhs: Rc<Expression>) -> Rc<Expression> {
+    if let (Some(a), Some(b)) = (lhs.literal(), rhs.literal()) {
+        if let Some(result) = operator.apply(a, b) {
+            return literal(result);
+        }
+    }
+
+    let (a_min, a_max) = lhs.range();
+    let (b_min, b_max) = rhs.range();
+    match (operator, lhs.literal(), rhs.literal()) {
+        (Operator::Add, Some(0), _) | (Operator::Mul, Some(1), _) => return rhs,
+        (Operator::Add, _, Some(0)) | (Operator::Mul, _, Some(1)) | (Operator::Div, _, Some(1)) => return lhs,
+        (Operator::Mul, Some(0), _) | (Operator::Mul, _, Some(0)) => return literal(0),
+        (Operator::Div, _, Some(d)) if d > 0 => {
+            if a_min >= 0 && a_max < d {
+                return literal(0);
+            }
+            if let Some((a, _)) = lhs.split_multiple_of(d) {
+                return a.clone();
+            }
+        }
+        (Operator::Mod, _, Some(m)) if m > 0 => {
+            if a_min >= 0 && a_max < m {
+                return lhs;
+            }
+            if let Some((_, b)) = lhs.split_multiple_of(m) {
+                return b.clone();
+            }
+        }
+        (Operator::Eql, _, _) => {
+            if a_max < b_min || b_max < a_min {
+                return literal(0);
+            }
+            if Rc::ptr_eq(&lhs, &rhs) || lhs == rhs {
+                return literal(1);
+            }
+        }
+        _ => (),
+    }
+
+    let range = range_of(operator, (a_min, a_max), (b_min, b_max));
+    if range.0 == range.1 {
+        return literal(range.0);
+    }
+    Rc::new(Expression::Operation {
+        operator,
+        lhs,
+        rhs,
+        range,
+    })
+}
+
+/// The ALU with each register holding an `Expression` rather than a number.
+#[derive(Debug, Clone)]
+pub struct SymbolicComputer {
+    pub w: Rc<Expression>,
+    pub x: Rc<Expression>,
+    pub y: Rc<Expression>,
+    pub z: Rc<Expression>,
+    inputs_read: usize,
+}
+
+impl Display for SymbolicComputer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{{w: {}, x: {}, y: {}, z: {}}}", self.w, self.x, self.y, self.z)
+    }
+}
+
+#[allow(dead_code)]
+impl SymbolicComputer {
+    pub fn new() -> Self {
+        SymbolicComputer::from_registers([0, 0, 0, 0], 0)
+    }
+
+    /// Starts from known register values, as if `inputs_read` digits had already been read.
+    pub fn from_registers([w, x, y, z]: [i64; 4], inputs_read: usize) -> Self {
+        SymbolicComputer {
+            w: literal(w),
+            x: literal(x),
+            y: literal(y),
+            z: literal(z),
+            inputs_read,
+        }
+    }
+
+    fn register(&self, variable: Variable) -> &Rc<Expression> {
+        match variable {
+            Variable::W => &self.w,
+            Variable::X => &self.x,
+            Variable::Y => &self.y,
+            Variable::Z => &self.z,
+        }
+    }
+
+    fn register_mut(&mut self, variable: Variable) -> &mut Rc<Expression> {
+        match variable {
+            Variable::W => &mut self.w,
+            Variable::X => &mut self.x,
+            Variable::Y => &mut self.y,
+            Variable::Z => &mut self.z,
+        }
+    }
+
+    fn deconstruct_rhs(&self, rhs: Rhs) -> Rc<Expression> {
+        match rhs {
+            Rhs::Literal(n) => literal(n),
+            Rhs::Variable(variable) => self.register(variable).clone(),
+        }
+    }
+
+    pub fn run(&mut self, instructions: &[Instruction]) {
+        for instruction in instructions {
+            self.run_instruction(*instruction);
+        }
+    }
+
+    pub fn run_instruction(&mut self, instruction: Instruction) {
+        let (variable, operator, rhs) = match instruction {
+            Instruction::Inp(variable) => {
+                *self.register_mut(variable) = Rc::new(Expression::Input(self.inputs_read));
+                self.inputs_read += 1;
+                return;
+            }
+            Instruction::Add(variable, rhs) => (variable, Operator::Add, rhs),
+            Instruction::Mul(variable, rhs) => (variable, Operator::Mul, rhs),
+            Instruction::Div(variable, rhs) => (variable, Operator::Div, rhs),
+            Instruction::Mod(variable, rhs) => (variable, Operator::Mod, rhs),
+            Instruction::Eql(variable, rhs) => (variable, Operator::Eql, rhs),
+        };
+        let rhs = self.deconstruct_rhs(rhs);
+        let lhs = self.register(variable).clone();
+        *self.register_mut(variable) = operate(operator, lhs, rhs);
+    }
+}
+
+/// Finds the largest (or smallest) digits that an arbitrary ALU program accepts, meaning it finishes with
+/// `z` at 0. Digits are tried one at a time from the most significant, running the program concretely up
+/// to the next `inp` and then symbolically to the end to check that 0 is still a possible value of `z`.
+pub fn solve(instructions: &[Instruction], largest: bool) -> Option<Vec<i64>> {
+    let mut segments: Vec<&[Instruction]> = vec![];
+    let mut start = 0;
+    for (i, instruction) in instructions.iter().enumerate() {
+        if matches!(instruction, Instruction::Inp(_)) {
+            segments.push(&instructions[start..i]);
+            start = i;
+        }
+    }
+    segments.push(&instructions[start..]);
+    let compiled: Vec<CompiledProgram> = segments
+        .iter()
+        .map(|segment| CompiledProgram::compile(&optimise(segment, Context::fragment())))
+        .collect();
+
+    let registers = compiled[0].run_from([0; 4], &[])?;
+    let digits: Vec<i64> = if largest {
+        (1..=9).rev().collect()
+    } else {
+        (1..=9).collect()
+    };
+    let mut dead_ends = HashSet::new();
+    let mut chosen = vec![];
+    if search(
+        &segments[1..],
+        &compiled[1..],
+        registers,
+        &digits,
+        &mut chosen,
+        &mut dead_ends,
+    ) {
+        Some(chosen)
+    } else {
+        None
+    }
+}
+
+fn search(
+    segments: &[&[Instruction]],
+    compiled: &[CompiledProgram],
+    registers: [i64; 4],
+    digits: &[i64],
+    chosen: &mut Vec<i64>,
+    dead_ends: &mut HashSet<(usize, [i64; 4])>,
+) -> bool {
+    let (segment, rest) = match compiled.split_first() {
+        Some(split) => split,
+        None => return registers[3] == 0,
+    };
+    if dead_ends.contains(&(chosen.len(), registers)) {
+        return false;
+    }
+
+    let mut symbolic = SymbolicComputer::from_registers(registers, chosen.len());
+    for remaining in segments {
+        symbolic.run(remaining);
+    }
+    if symbolic.z.could_be(0) {
+        for digit in digits {
+            let next = match segment.run_from(registers, &[*digit]) {
+                Some(next) => next,
+                None => continue,
+            };
+            chosen.push(*digit);
+            if search(&segments[1..], rest, next, digits, chosen, dead_ends) {
+                return true;
+            }
+            chosen.pop();
+        }
+    }
+    dead_ends.insert((chosen.len(), registers));
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::parse_from_str;
+    use super::*;
+
+    #[test]
+    fn test_constant_folding() {
+        let mut computer = SymbolicComputer::new();
+        computer.run(&parse_from_str(
+            "inp w\nmul x 0\nadd x z\nmod x 26\nadd x 12\neql x w\neql x 0",
+        ));
+        assert_eq!(*computer.x, Expression::Literal(1));
+        computer.run(&parse_from_str(
+            "mul y 0\nadd y 25\nmul y x\nadd y 1\nmul z y\nmul y 0\nadd y w\nadd y 7",
+        ));
+        assert_eq!(*computer.z, Expression::Literal(0));
+        assert_eq!(format!("{}", computer.y), "(in[0] + 7)");
+        assert_eq!(computer.y.range(), (8, 16));
+    }
+
+    #[test]
+    fn test_push_then_pop() {
+        let mut computer = SymbolicComputer::new();
+        computer.run(&parse_from_str("inp w\nadd z w\nadd z 3\ninp x\nmul z 26\nadd z x"));
+        assert_eq!(format!("{}", computer.z), "(((in[0] + 3) * 26) + in[1])");
+        assert_eq!(computer.z.range(), (4 * 26 + 1, 12 * 26 + 9));
+        computer.run(&parse_from_str("mod z 26"));
+        assert_eq!(*computer.z, Expression::Input(1));
+
+        let mut computer = SymbolicComputer::new();
+        computer.run(&parse_from_str(
+            "inp w\nadd z w\nadd z 3\ninp x\nmul z 26\nadd z x\ndiv z 26\neql z w",
+        ));
+        assert_eq!(format!("{}", computer.z), "((in[0] + 3) == in[0])");
+        assert_eq!(computer.z.range(), (0, 1));
+    }
+
+    #[test]
+    fn test_ranges_decide_equality() {
+        let mut computer = SymbolicComputer::new();
+        computer.run(&parse_from_str("inp w\ninp x\nadd x 10\neql x w"));
+        assert_eq!(*computer.x, Expression::Literal(0));
+        computer.run(&parse_from_str("inp y\neql y w"));
+        assert_eq!(computer.y.range(), (0, 1));
+        assert_eq!(computer.y.inputs(), vec![0, 2].into_iter().collect());
+        assert_eq!(computer.y.evaluate(&[4, 1, 4]), Some(1));
+        assert_eq!(computer.y.evaluate(&[4, 1, 5]), Some(0));
+    }
+
+    #[test]
+    fn test_folding_agrees_with_evaluate() {
+        let mut computer = SymbolicComputer::new();
+        computer.run(&parse_from_str("inp x\nadd x -5\nmul x 1000000000000\nmul x x"));
+        assert_eq!(computer.x.range(), FULL_RANGE);
+        assert_eq!(computer.x.evaluate(&[5]), Some(0));
+        assert_eq!(computer.x.evaluate(&[9]), None);
+        computer.run(&parse_from_str("div x -1"));
+        assert_eq!(computer.x.range(), FULL_RANGE);
+
+        for (operator, a, b) in [
+            (Operator::Div, i64::MIN, -1),
+            (Operator::Div, 7, 0),
+            (Operator::Add, i64::MAX, 1),
+            (Operator::Mul, i64::MIN, 2),
+            (Operator::Mod, -7, 2),
+            (Operator::Mod, 7, -2),
+        ] {
+            let folded = operate(operator, literal(a), literal(b));
+            assert_eq!(folded.literal(), None);
+            assert_eq!(folded.evaluate(&[]), None);
+        }
+        assert_eq!(
+            *operate(Operator::Div, literal(7), literal(-2)),
+            Expression::Literal(-3)
+        );
+    }
+
+    #[test]
+    fn test_solve_arbitrary_program() {
+        // Accepts inputs where the first digit is twice the second and the third is odd.
+        let program = parse_from_str(
+            "inp w\ninp x\nmul x 2\neql x w\neql x 0\ninp y\nmod y 2\neql y 1\neql y 0\nadd z x\nadd z y",
+        );
+        assert_eq!(solve(&program, true), Some(vec![8, 4, 9]));
+        assert_eq!(solve(&program, false), Some(vec![2, 1, 1]));
+        let impossible = parse_from_str("inp w\nadd z w\nadd z 9\nmod z 20");
+        assert_eq!(solve(&impossible, true), None);
+    }
+}
@@ -5,6 +5,11 @@ use std::fs::read_to_string;
 use std::ops::Index;
 use std::path::Path;
 use itertools::Itertools;
+use crate::helpers::render::{render, Frame, Output};
+use bitvec::prelude::*;
+use hashbrown::HashMap;
+use rand::Rng;
+use std::fmt::{Display, Formatter};
 
 type Input = Vec<Vec<Option<Direction>>>;
 pub fn solve_part_1() -> Result<(), ()> {
@@ -82,20 +87,6 @@ fn move_downers(seabed: &mut Vec<Vec<Option<Direction>>>) -> bool {
     movement_occurred
 }
 
-fn print_seabed(seabed: &[Vec<Option<Direction>>]) {
-    for row in seabed {
-        println!();
-        for item in row {
-            match item {
-                Some(Direction::Down) => print!("{}", 'v'),
-                Some(Direction::Right) => print!("{}", '>'),
-                None => print!("{}", '.'),
-            }
-        }
-    }
-    println!();
-}
-
 pub fn part_one(mut input: Input) -> usize {
     let mut movement_occurred = true;
     let mut steps = 0;
@@ -108,8 +99,206 @@ pub fn part_one(mut input: Input) -> usize {
     steps
 }
 
-pub fn part_two(input: Input) -> usize {
-    unimplemented!();
+/// How many cucumbers of each herd moved in a step.
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub struct Movement {
+    pub right: usize,
+    pub down: usize,
+}
+
+impl Movement {
+    pub fn total(&self) -> usize {
+        self.right + self.down
+    }
+}
+
+/// Whether the herds eventually stop, or end up going round in circles forever.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Ending {
+    Settled { steps: usize },
+    Cycle { start: usize, period: usize },
+}
+
+impl Display for Ending {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Ending::Settled { steps } => write!(f, "Settled on step {}", steps),
+            Ending::Cycle { start, period } => write!(f, "Repeats every {} steps from step {}", period, start),
+        }
+    }
+}
+
+/// The seabed with each herd kept as a row of bits, so that a whole row moves at once.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Seabed {
+    width: usize,
+    height: usize,
+    rights: Vec<BitVec>,
+    downs: Vec<BitVec>,
+}
+
+#[allow(dead_code)]
+impl Seabed {
+    pub fn new(width: usize, height: usize) -> Self {
+        Seabed {
+            width,
+            height,
+            rights: vec![BitVec::repeat(false, width); height],
+            downs: vec![BitVec::repeat(false, width); height],
+        }
+    }
+
+    /// Fills each space with a cucumber with probability `density`, split evenly between the herds.
+    pub fn random<R: Rng>(width: usize, height: usize, density: f64, rng: &mut R) -> Self {
+        let mut seabed = Seabed::new(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                if rng.gen_bool(density) {
+                    let direction = if rng.gen() { Direction::Right } else { Direction::Down };
+                    seabed.set((y, x), Some(direction));
+                }
+            }
+        }
+        seabed
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, (y, x): (usize, usize)) -> Option<Direction> {
+        if self.rights[y][x] {
+            Some(Direction::Right)
+        } else if self.downs[y][x] {
+            Some(Direction::Down)
+        } else {
+            None
+        }
+    }
+
+    pub fn set(&mut self, (y, x): (usize, usize), item: Option<Direction>) {
+        self.rights[y].set(x, item == Some(Direction::Right));
+        self.downs[y].set(x, item == Some(Direction::Down));
+    }
+
+    fn occupied(&self, y: usize) -> BitVec {
+        self.rights[y].clone() | self.downs[y].clone()
+    }
+
+    fn move_righties(&mut self) -> usize {
+        let mut moved = 0;
+        for y in 0..self.height {
+            let mut ahead = self.occupied(y);
+            ahead.rotate_left(1);
+            let movers = self.rights[y].clone() & !ahead;
+            moved += movers.count_ones();
+            let mut arrived = movers.clone();
+            arrived.rotate_right(1);
+            self.rights[y] &= !movers;
+            self.rights[y] |= arrived;
+        }
+        moved
+    }
+
+    fn move_downers(&mut self) -> usize {
+        let movers: Vec<BitVec> = (0..self.height)
+            .map(|y| self.downs[y].clone() & !self.occupied((y + 1) % self.height))
+            .collect();
+        for y in 0..self.height {
+            self.downs[y] &= !movers[y].clone();
+            self.downs[y] |= movers[(y + self.height - 1) % self.height].clone();
+        }
+        movers.iter().map(|row| row.count_ones()).sum()
+    }
+
+    pub fn step(&mut self) -> Movement {
+        let right = self.move_righties();
+        let down = self.move_downers();
+        Movement { right, down }
+    }
+
+    /// Steps until nothing moves, or until the seabed is back in a state it has already been in.
+    pub fn run(&mut self) -> Ending {
+        let mut seen = HashMap::new();
+        let mut steps = 0;
+        loop {
+            if let Some(start) = seen.insert(self.clone(), steps) {
+                return Ending::Cycle {
+                    start,
+                    period: steps - start,
+                };
+            }
+            let movement = self.step();
+            steps += 1;
+            if movement.total() == 0 {
+                return Ending::Settled { steps };
+            }
+        }
+    }
+
+    pub fn frame(&self) -> Frame {
+        Frame::from_fn(self.width, self.height, |position| match self.get(position) {
+            Some(Direction::Right) => ('>', [255, 140, 0]),
+            Some(Direction::Down) => ('v', [0, 200, 200]),
+            None => ('.', [0, 0, 60]),
+        })
+    }
+
+    /// Every state of the seabed from this one on, finishing once nothing moves.
+    pub fn frames(self) -> Frames {
+        Frames { seabed: Some(self) }
+    }
+}
+
+pub struct Frames {
+    seabed: Option<Seabed>,
+}
+
+impl Iterator for Frames {
+    type Item = Seabed;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let frame = self.seabed.take()?;
+        let mut next = frame.clone();
+        if next.step().total() > 0 {
+            self.seabed = Some(next);
+        }
+        Some(frame)
+    }
+}
+
+impl From<&Input> for Seabed {
+    fn from(input: &Input) -> Self {
+        let mut seabed = Seabed::new(input[0].len(), input.len());
+        for (y, row) in input.iter().enumerate() {
+            for (x, item) in row.iter().enumerate() {
+                seabed.set((y, x), *item);
+            }
+        }
+        seabed
+    }
+}
+
+impl Display for Seabed {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.frame())
+    }
+}
+
+/// Renders the herds migrating until they settle, or for 1000 steps if they never do.
+pub fn animate(output: &Output) -> Result<(), ()> {
+    let seabed = Seabed::from(&parse_from_file("./inputs/day25.txt"));
+    let frames = seabed.frames().take(1000).map(|seabed| seabed.frame());
+    render(frames, output).map_err(|error| println!("{}", error))
+}
+
+/// There's no second puzzle on the last day, so this says how the herds end up, settling or cycling.
+pub fn part_two(input: Input) -> Ending {
+    Seabed::from(&input).run()
 }
 
 #[cfg(test)]
@@ -123,6 +312,66 @@ mod tests {
         assert_eq!(part_one(input), 58);
     }
 
+    #[test]
+    fn test_part_two() {
+        const TEST_INPUT: &str = include_str!("../../test_inputs/day25.txt");
+        let input = parse_from_str(TEST_INPUT);
+        assert_eq!(part_two(input), Ending::Settled { steps: 58 });
+    }
+
+    #[test]
+    fn test_seabed_matches_grid() {
+        const TEST_INPUT: &str = include_str!("../../test_inputs/day25.txt");
+        let mut input = parse_from_str(TEST_INPUT);
+        let mut seabed = Seabed::from(&input);
+        assert_eq!(
+            seabed.to_string(),
+            TEST_INPUT.lines().map(|line| format!("{}\n", line)).collect::<String>()
+        );
+        for _ in 0..60 {
+            let moved = move_righties(&mut input) | move_downers(&mut input);
+            let movement = seabed.step();
+            assert_eq!(moved, movement.total() > 0);
+            assert_eq!(seabed, Seabed::from(&input));
+        }
+    }
+
+    #[test]
+    fn test_movement_and_frames() {
+        let mut seabed = Seabed::from(&parse_from_str("...>>>>>..."));
+        assert_eq!(seabed.step(), Movement { right: 1, down: 0 });
+        assert_eq!(seabed.to_string(), "...>>>>.>..\n");
+        assert_eq!(seabed.step(), Movement { right: 2, down: 0 });
+        assert_eq!(seabed.to_string(), "...>>>.>.>.\n");
+
+        let frames: Vec<String> = Seabed::from(&parse_from_str("..........\n.>v....v..\n.......>..\n.........."))
+            .frames()
+            .take(2)
+            .map(|frame| frame.to_string())
+            .collect();
+        assert_eq!(frames[0], "..........\n.>v....v..\n.......>..\n..........\n");
+        assert_eq!(frames[1], "..........\n.>........\n..v....v>.\n..........\n");
+        assert_eq!(Seabed::from(&parse_from_str(">>v\nv>>")).frames().count(), 1);
+    }
+
+    #[test]
+    fn test_cycles() {
+        let input = parse_from_str(">.\n..");
+        assert_eq!(part_two(input), Ending::Cycle { start: 0, period: 2 });
+        let input = parse_from_str("v.\n..\n..");
+        assert_eq!(part_two(input), Ending::Cycle { start: 0, period: 3 });
+    }
+
+    #[test]
+    fn test_random_seabed() {
+        let mut rng = rand::thread_rng();
+        let seabed = Seabed::random(40, 30, 0.3, &mut rng);
+        let input = parse_from_str(&seabed.to_string());
+        assert_eq!(Seabed::from(&input), seabed);
+        let full = Seabed::random(5, 5, 1.0, &mut rng);
+        assert!(!full.to_string().contains('.'));
+    }
+
     #[ignore]
     #[test]
     fn test_parse() {
@@ -1,61 +1,52 @@
-use hashbrown::HashSet;
+use hashbrown::HashMap;
 
 use std::fs;
 use std::path::Path;
 
+/// A square bingo board, its numbers kept row by row.
 #[derive(Debug, Eq, PartialEq, Clone)]
-pub struct Grid {
-    columns: Vec<HashSet<usize>>,
-    rows: Vec<HashSet<usize>>,
+pub struct Board {
+    size: usize,
+    numbers: Vec<usize>,
 }
 
-impl Grid {
-    pub fn row_or_column_is_subset_of(&self, to_check: &HashSet<usize>) -> bool {
-        self.columns.iter().any(|column| column.is_subset(to_check))
-            || self.rows.iter().any(|row| row.is_subset(to_check))
-    }
-
-    pub fn calculate_result_from_grid(&self, called: &[usize]) -> usize {
-        let called_set: HashSet<_> = called.iter().copied().collect();
-        let all_in_grid: HashSet<_> = self.rows.iter().flat_map(|row| row.iter().copied()).collect();
-        let remaining: Vec<usize> = all_in_grid.difference(&called_set).copied().collect();
-        remaining.iter().sum::<usize>() * called.last().unwrap()
+impl Board {
+    pub fn from_rows(rows: &[Vec<usize>]) -> Self {
+        let size = rows.len();
+        if rows.iter().any(|row| row.len() != size) {
+            panic!("You wot? A board should be {} numbers wide", size);
+        }
+        Board {
+            size,
+            numbers: rows.concat(),
+        }
     }
 }
 
-pub fn solve_part_1() -> Result<(), ()> {
+type Input = (Vec<usize>, Vec<Board>);
+pub fn solve_part_1(diagonals: bool) -> Result<(), ()> {
     let input = parse_from_file("./inputs/day4.txt");
-    println!("Solution: {}", part_one(input));
+    println!("Solution: {}", part_one(input, diagonals));
     Ok(())
 }
 
-pub fn solve_part_2() -> Result<(), ()> {
+pub fn solve_part_2(diagonals: bool) -> Result<(), ()> {
     let input = parse_from_file("./inputs/day4.txt");
-    println!("Solution: {}", part_two(input));
+    println!("Solution: {}", part_two(input, diagonals));
     Ok(())
 }
 
-fn parse_from_file<T: AsRef<Path>>(filename: T) -> (Vec<usize>, Vec<Grid>) {
+fn parse_from_file<T: AsRef<Path>>(filename: T) -> Input {
     let input = fs::read_to_string(filename).unwrap();
     parse_from_str(&input)
 }
 
-fn parse_grid_row(line: &str) -> HashSet<usize> {
+fn parse_grid_row(line: &str) -> Vec<usize> {
     line.split_whitespace().map(|number| number.parse().unwrap()).collect()
 }
 
-fn parse_grid_columns(input: &[&str]) -> Vec<HashSet<usize>> {
-    (0..5)
-        .map(|i| {
-            input
-                .iter()
-                .map(|line| line.split_whitespace().nth(i).unwrap().parse().unwrap())
-                .collect()
-        })
-        .collect()
-}
-
-fn parse_from_str(input: &str) -> (Vec<usize>, Vec<Grid>) {
+/// The numbers to call, then the boards, each as many lines as it is wide and split up by blank lines.
+fn parse_from_str(input: &str) -> Input {
     let mut iter = input.lines();
     let numbers = iter
         .by_ref()
@@ -65,49 +56,129 @@ fn parse_from_str(input: &str) -> (Vec<usize>, Vec<Grid>) {
         .map(|number| number.parse().unwrap())
         .collect();
 
-    let just_grids: Vec<&str> = iter.filter(|line| !line.is_empty()).collect();
-    let grids = just_grids
-        .chunks(5)
-        .map(|lines| -> Grid {
-            let rows = lines.iter().map(|line| parse_grid_row(*line)).collect();
-            let columns = parse_grid_columns(lines);
-            Grid { rows, columns }
-        })
-        .collect::<Vec<_>>();
-    (numbers, grids)
+    let mut boards = vec![];
+    let mut rows = vec![];
+    for line in iter.chain(std::iter::once("")) {
+        if line.trim().is_empty() {
+            if !rows.is_empty() {
+                boards.push(Board::from_rows(&rows));
+                rows.clear();
+            }
+        } else {
+            rows.push(parse_grid_row(line));
+        }
+    }
+    (numbers, boards)
+}
+
+/// A board getting a full line.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Win {
+    pub board: usize,
+    /// Where the winning number is in the order they're called.
+    pub draw: usize,
+    pub score: usize,
 }
 
-pub fn part_one((numbers, grids): (Vec<usize>, Vec<Grid>)) -> usize {
-    let mut size = 5;
-    loop {
-        let as_called = &numbers[0..size];
-        let as_set: HashSet<usize> = as_called.iter().copied().collect();
-        let result = grids.iter().find(|grid| grid.row_or_column_is_subset_of(&as_set));
-        if let Some(grid) = result {
-            break grid.calculate_result_from_grid(as_called);
+/// How far along each line of a board is, and what its unmarked numbers add up to.
+#[derive(Debug, Clone)]
+struct Card {
+    size: usize,
+    marked_in_line: Vec<usize>,
+    unmarked_sum: usize,
+    won: bool,
+}
+
+/// Bingo on any number of boards of any size. Each number is looked up to find the cells it marks, so
+/// calling one only touches the boards it's on.
+#[derive(Debug, Clone)]
+pub struct Bingo {
+    cards: Vec<Card>,
+    /// Each number's cells as (board, row, column).
+    cells: HashMap<usize, Vec<(usize, usize, usize)>>,
+    diagonals: bool,
+}
+
+impl Bingo {
+    pub fn new(boards: &[Board], diagonals: bool) -> Self {
+        let mut cells: HashMap<usize, Vec<(usize, usize, usize)>> = HashMap::new();
+        for (board, Board { size, numbers }) in boards.iter().enumerate() {
+            for (i, number) in numbers.iter().enumerate() {
+                cells.entry(*number).or_default().push((board, i / size, i % size));
+            }
+        }
+        let cards = boards
+            .iter()
+            .map(|board| Card {
+                size: board.size,
+                marked_in_line: vec![0; 2 * board.size + 2],
+                unmarked_sum: board.numbers.iter().sum(),
+                won: false,
+            })
+            .collect();
+        Bingo {
+            cards,
+            cells,
+            diagonals,
         }
-        size += 1
     }
-}
 
-pub fn part_two((numbers, grids): (Vec<usize>, Vec<Grid>)) -> usize {
-    let final_grid = reduce_to_one_grid(&numbers, 5, grids);
-    // Play until the end
-    part_one((numbers, vec![final_grid]))
+    /// Marks the number everywhere it is, returning the boards that it wins for. The lines are the rows, then
+    /// the columns, then the two diagonals. Every cell is marked, even on a board that's already won, so a
+    /// number that's on a board twice comes off its unmarked sum both times.
+    pub fn call(&mut self, number: usize) -> Vec<usize> {
+        let mut winners = vec![];
+        for (board, row, column) in self.cells.remove(&number).unwrap_or_default() {
+            let card = &mut self.cards[board];
+            card.unmarked_sum -= number;
+            let size = card.size;
+            let mut lines = vec![row, size + column];
+            if self.diagonals && row == column {
+                lines.push(2 * size);
+            }
+            if self.diagonals && row + column == size - 1 {
+                lines.push(2 * size + 1);
+            }
+            for line in lines {
+                card.marked_in_line[line] += 1;
+                if card.marked_in_line[line] == size && !card.won {
+                    card.won = true;
+                    winners.push(board);
+                }
+            }
+        }
+        winners.sort_unstable();
+        winners
+    }
+
+    pub fn unmarked_sum(&self, board: usize) -> usize {
+        self.cards[board].unmarked_sum
+    }
 }
 
-fn reduce_to_one_grid(all_numbers: &[usize], position: usize, grids: Vec<Grid>) -> Grid {
-    if grids.len() == 1 {
-        return grids.first().unwrap().clone();
+/// Every board's win in the order they happen, with boards that win on the same number in board order.
+/// Boards that never get a line are left out.
+pub fn play(numbers: &[usize], boards: &[Board], diagonals: bool) -> Vec<Win> {
+    let mut bingo = Bingo::new(boards, diagonals);
+    let mut wins = vec![];
+    for (draw, number) in numbers.iter().enumerate() {
+        for board in bingo.call(*number) {
+            wins.push(Win {
+                board,
+                draw,
+                score: bingo.unmarked_sum(board) * number,
+            });
+        }
     }
-    let called = &all_numbers[0..position];
-    let as_set: HashSet<usize> = called.iter().copied().collect();
-    let new_grids = grids
-        .iter()
-        .filter(|grid| !grid.row_or_column_is_subset_of(&as_set))
-        .cloned()
-        .collect();
-    reduce_to_one_grid(all_numbers, position + 1, new_grids)
+    wins
+}
+
+pub fn part_one((numbers, boards): Input, diagonals: bool) -> usize {
+    play(&numbers, &boards, diagonals).first().expect("Nobody won").score
+}
+
+pub fn part_two((numbers, boards): Input, diagonals: bool) -> usize {
+    play(&numbers, &boards, diagonals).last().expect("Nobody won").score
 }
 
 #[cfg(test)]
@@ -125,12 +196,87 @@ mod tests {
     #[test]
     fn test_part_one() {
         let input = parse_from_str(TEST_INPUT);
-        assert_eq!(part_one(input), 4512);
+        assert_eq!(part_one(input, false), 4512);
     }
 
     #[test]
     fn test_part_two() {
         let input = parse_from_str(TEST_INPUT);
-        assert_eq!(part_two(input), 1924);
+        assert_eq!(part_two(input, false), 1924);
+    }
+
+    #[test]
+    fn test_winning_order() {
+        let (numbers, boards) = parse_from_str(TEST_INPUT);
+        let wins = play(&numbers, &boards, false);
+        let order: Vec<(usize, usize)> = wins.iter().map(|win| (win.board, win.draw)).collect();
+        assert_eq!(order, vec![(2, 11), (0, 13), (1, 14)]);
+        assert_eq!(wins[0].score, 4512);
+        assert_eq!(wins[2].score, 1924);
+    }
+
+    #[test]
+    fn test_other_sizes_and_diagonals() {
+        let (numbers, boards) = parse_from_str("5,1,9,3,7\n\n1 2 3\n4 5 6\n7 8 9\n\n9 8\n7 6\n");
+        assert_eq!(boards.len(), 2);
+        let wins = play(&numbers, &boards, false);
+        assert_eq!(
+            wins,
+            vec![Win {
+                board: 1,
+                draw: 4,
+                score: (8 + 6) * 7
+            }]
+        );
+
+        let wins = play(&numbers, &boards, true);
+        assert_eq!(
+            wins,
+            vec![
+                Win {
+                    board: 0,
+                    draw: 2,
+                    score: (2 + 3 + 4 + 6 + 7 + 8) * 9
+                },
+                Win {
+                    board: 1,
+                    draw: 4,
+                    score: (8 + 6) * 7
+                },
+            ]
+        );
+
+        // A board only wins once, even when a number finishes two of its lines.
+        let (numbers, boards) = parse_from_str("2,3,1,4\n\n1 2\n3 4\n");
+        let wins = play(&numbers, &boards, false);
+        assert_eq!(
+            wins,
+            vec![Win {
+                board: 0,
+                draw: 2,
+                score: 4
+            }]
+        );
+    }
+
+    #[test]
+    fn test_repeated_number() {
+        // The 2 that finishes the top row is also under the 1, and both come off the score.
+        let (numbers, boards) = parse_from_str("1,2\n\n1 2\n2 3\n");
+        let wins = play(&numbers, &boards, false);
+        assert_eq!(
+            wins,
+            vec![Win {
+                board: 0,
+                draw: 1,
+                score: 3 * 2
+            }]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "You wot?")]
+    fn test_ragged_board() {
+        parse_from_str("1,2\n\n1 2\n3\n");
     }
 }
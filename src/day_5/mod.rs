@@ -1,9 +1,13 @@
-use counter::Counter;
-use std::cmp::{max, min};
-use std::fs;
+use hashbrown::HashMap;
+use num::integer::gcd;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
 use std::path::Path;
 
-type Input = Vec<((usize, usize), (usize, usize))>;
+/// An (x, y) position on the sea floor.
+type Point = (i64, i64);
+type Line = (Point, Point);
+type Input = Vec<Line>;
 
 pub fn solve_part_1() -> Result<(), ()> {
     let input = parse_from_file("./inputs/day5.txt");
@@ -22,6 +26,19 @@ fn parse_from_file<T: AsRef<Path>>(filename: T) -> Input {
     parse_from_str(&input)
 }
 
+/// Saves how many vent lines cross each point as a greyscale PGM image.
+pub fn save_heatmap(part: usize, path: &Path) -> Result<(), ()> {
+    let input = parse_from_file("./inputs/day5.txt");
+    let lines: Vec<Line> = match part {
+        1 => input.into_iter().filter(is_straight).collect(),
+        _ => input,
+    };
+    let overlaps = count_overlaps(&lines);
+    File::create(path)
+        .and_then(|file| write_pgm(&mut BufWriter::new(file), &overlaps))
+        .map_err(|error| println!("{}", error))
+}
+
 fn parse_from_str(input: &str) -> Input {
     input
         .lines()
@@ -38,60 +55,191 @@ fn parse_from_str(input: &str) -> Input {
         .collect()
 }
 
-enum Gradient {
-    Positive,
-    Negative,
+/// Every whole point on the line, from start to end. Dividing the line's run and rise by their greatest
+/// common divisor gives the smallest step that lands on whole points, whatever the slope.
+pub fn rasterise(((x1, y1), (x2, y2)): Line) -> impl Iterator<Item = Point> {
+    let (dx, dy) = (x2 - x1, y2 - y1);
+    let steps = gcd(dx, dy);
+    let (step_x, step_y) = if steps == 0 { (0, 0) } else { (dx / steps, dy / steps) };
+    (0..=steps).map(move |i| (x1 + i * step_x, y1 + i * step_y))
 }
 
-fn get_diagonal_spaces((x1, y1): (usize, usize), (x2, y2): (usize, usize)) -> Box<dyn Iterator<Item = (usize, usize)>> {
-    let dx = if x2 > x1 { 1 } else { -1 };
-    let dy = if y2 > y1 { 1 } else { -1 };
-    let gradient = if dy == dx {
-        Gradient::Positive
-    } else {
-        Gradient::Negative
-    };
-    let ((start_x, start_y), (end_x, _)) = if x1 < x2 {
-        ((x1, y1), (x2, y2))
-    } else {
-        ((x2, y2), (x1, y1))
-    };
+fn is_straight(((x1, y1), (x2, y2)): &Line) -> bool {
+    x1 == x2 || y1 == y2
+}
+
+/// The smallest box holding all the points, corners included.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Bounds {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Bounds {
+    pub fn of<I: IntoIterator<Item = Point>>(points: I) -> Option<Self> {
+        points.into_iter().fold(None, |bounds, (x, y)| {
+            Some(match bounds {
+                None => Bounds {
+                    min: (x, y),
+                    max: (x, y),
+                },
+                Some(Bounds { min, max }) => Bounds {
+                    min: (min.0.min(x), min.1.min(y)),
+                    max: (max.0.max(x), max.1.max(y)),
+                },
+            })
+        })
+    }
+
+    /// The number of points in the box, or `None` if there are too many to count in a `u64`.
+    pub fn area(&self) -> Option<u64> {
+        let width = self.max.0.checked_sub(self.min.0)?.checked_add(1)?;
+        let height = self.max.1.checked_sub(self.min.1)?.checked_add(1)?;
+        (width as u64).checked_mul(height as u64)
+    }
+
+    pub fn width(&self) -> usize {
+        (self.max.0 - self.min.0 + 1) as usize
+    }
+
+    pub fn height(&self) -> usize {
+        (self.max.1 - self.min.1 + 1) as usize
+    }
+
+    pub fn contains(&self, (x, y): Point) -> bool {
+        (self.min.0..=self.max.0).contains(&x) && (self.min.1..=self.max.1).contains(&y)
+    }
+
+    fn index(&self, (x, y): Point) -> usize {
+        (y - self.min.1) as usize * self.width() + (x - self.min.0) as usize
+    }
+}
+
+/// How many lines cross each point. Small areas get a count for every point, but lines spread far apart
+/// only keep counts for the points they cross.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Overlaps {
+    Dense { bounds: Bounds, counts: Vec<u32> },
+    Sparse(HashMap<Point, u32>),
+}
+
+impl Overlaps {
+    /// The most points a dense count will hold.
+    pub const DENSE_LIMIT: u64 = 1 << 24;
+
+    pub fn dense(bounds: Bounds) -> Self {
+        Overlaps::Dense {
+            bounds,
+            counts: vec![0; bounds.width() * bounds.height()],
+        }
+    }
+
+    pub fn sparse() -> Self {
+        Overlaps::Sparse(HashMap::new())
+    }
+
+    /// Picks dense counts if every endpoint fits in a small enough box.
+    pub fn for_lines(lines: &[Line]) -> Self {
+        match Bounds::of(lines.iter().flat_map(|(start, end)| [*start, *end])) {
+            Some(bounds) if matches!(bounds.area(), Some(area) if area <= Overlaps::DENSE_LIMIT) => {
+                Overlaps::dense(bounds)
+            }
+            _ => Overlaps::sparse(),
+        }
+    }
+
+    pub fn add(&mut self, point: Point) {
+        match self {
+            Overlaps::Dense { bounds, counts } => {
+                if !bounds.contains(point) {
+                    panic!("You wot? {:?} is outside {:?}", point, bounds);
+                }
+                counts[bounds.index(point)] += 1;
+            }
+            Overlaps::Sparse(counts) => *counts.entry(point).or_insert(0) += 1,
+        }
+    }
+
+    pub fn count(&self, point: Point) -> u32 {
+        match self {
+            Overlaps::Dense { bounds, counts } if bounds.contains(point) => counts[bounds.index(point)],
+            Overlaps::Dense { .. } => 0,
+            Overlaps::Sparse(counts) => counts.get(&point).copied().unwrap_or(0),
+        }
+    }
+
+    /// The number of points that at least two lines cross.
+    pub fn overlapping(&self) -> usize {
+        match self {
+            Overlaps::Dense { counts, .. } => counts.iter().filter(|count| **count >= 2).count(),
+            Overlaps::Sparse(counts) => counts.values().filter(|count| **count >= 2).count(),
+        }
+    }
+
+    pub fn max(&self) -> u32 {
+        match self {
+            Overlaps::Dense { counts, .. } => counts.iter().copied().max().unwrap_or(0),
+            Overlaps::Sparse(counts) => counts.values().copied().max().unwrap_or(0),
+        }
+    }
+
+    /// The box around the points that lines cross.
+    pub fn bounds(&self) -> Option<Bounds> {
+        match self {
+            Overlaps::Dense { bounds, .. } => Some(*bounds),
+            Overlaps::Sparse(counts) => Bounds::of(counts.keys().copied()),
+        }
+    }
+}
 
-    match gradient {
-        Gradient::Positive => Box::new((start_x..=end_x).enumerate().map(move |(i, x)| (x, start_y + i))),
-        Gradient::Negative => Box::new((start_x..=end_x).enumerate().map(move |(i, x)| (x, start_y - i))),
+pub fn count_overlaps(lines: &[Line]) -> Overlaps {
+    let mut overlaps = Overlaps::for_lines(lines);
+    for line in lines {
+        for point in rasterise(*line) {
+            overlaps.add(point);
+        }
     }
+    overlaps
 }
 
-fn get_visited((x1, y1): (usize, usize), (x2, y2): (usize, usize)) -> Box<dyn Iterator<Item = (usize, usize)>> {
-    match ((x1, y1), (x2, y2)) {
-        ((x1, y1), (x2, y2)) if x1 == x2 => Box::new((min(y1, y2)..=max(y1, y2)).map(move |y| (x1, y))),
-        ((x1, y1), (x2, y2)) if y1 == y2 => Box::new((min(x1, x2)..=max(x1, x2)).map(move |x| (x, y1))),
-        ((x1, y1), (x2, y2)) => Box::new(get_diagonal_spaces((x1, y1), (x2, y2))),
+/// Writes a binary PGM with a pixel per point, brighter where more lines cross and black where none do.
+/// Images with more pixels than a dense count would hold are refused, as lines far apart would need one
+/// mostly black pixel for every point in between.
+pub fn write_pgm<W: Write>(out: &mut W, overlaps: &Overlaps) -> io::Result<()> {
+    let bounds = match overlaps.bounds() {
+        Some(bounds) => bounds,
+        None => return write!(out, "P5\n0 0\n255\n"),
+    };
+    if !matches!(bounds.area(), Some(area) if area <= Overlaps::DENSE_LIMIT) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("A heatmap from {:?} to {:?} is too big to save", bounds.min, bounds.max),
+        ));
+    }
+    write!(out, "P5\n{} {}\n255\n", bounds.width(), bounds.height())?;
+    let max = overlaps.max().max(1) as u64;
+    for y in bounds.min.1..=bounds.max.1 {
+        let row: Vec<u8> = (bounds.min.0..=bounds.max.0)
+            .map(|x| (overlaps.count((x, y)) as u64 * 255 / max) as u8)
+            .collect();
+        out.write_all(&row)?;
     }
+    Ok(())
 }
 
 pub fn part_one(input: Input) -> usize {
-    let counts: Counter<(usize, usize)> = input
-        .iter()
-        .filter(|((x1, y1), (x2, y2))| x1 == x2 || y1 == y2)
-        .flat_map(|(start, end)| get_visited(*start, *end))
-        .collect();
-    counts.iter().filter(|(_, count)| **count >= 2).count()
+    let lines: Vec<Line> = input.into_iter().filter(is_straight).collect();
+    count_overlaps(&lines).overlapping()
 }
 
 pub fn part_two(input: Input) -> usize {
-    let counts: Counter<(usize, usize)> = input
-        .iter()
-        .flat_map(|(start, end)| get_visited(*start, *end))
-        .collect();
-    counts.iter().filter(|(_, count)| **count >= 2).count()
+    count_overlaps(&input).overlapping()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use hashbrown::HashSet;
+    use rand::Rng;
 
     const TEST_INPUT: &str = include_str!("../../test_inputs/day5.txt");
 
@@ -108,19 +256,97 @@ mod tests {
     }
 
     #[test]
-    fn test_get_diagonal_spaces() {
-        let correct: Vec<(usize, usize)> = vec![(1, 1), (2, 2)];
-        let correct: HashSet<_> = correct.into_iter().collect();
-        let result: HashSet<_> = get_diagonal_spaces((1, 1), (2, 2)).collect::<HashSet<_>>();
-        let result2: HashSet<_> = get_diagonal_spaces((2, 2), (1, 1)).collect::<HashSet<_>>();
-        assert_eq!(result, correct);
-        assert_eq!(result2, correct);
-
-        let correct: Vec<(usize, usize)> = vec![(0, 1), (1, 0)];
-        let correct: HashSet<_> = correct.into_iter().collect();
-        let result: HashSet<_> = get_diagonal_spaces((0, 1), (1, 0)).collect::<HashSet<_>>();
-        let result2: HashSet<_> = get_diagonal_spaces((1, 0), (0, 1)).collect::<HashSet<_>>();
-        assert_eq!(result, correct);
-        assert_eq!(result2, correct);
+    fn test_rasterise() {
+        let points: Vec<Point> = rasterise(((0, 0), (6, 3))).collect();
+        assert_eq!(points, vec![(0, 0), (2, 1), (4, 2), (6, 3)]);
+        let points: Vec<Point> = rasterise(((-2, 5), (1, -1))).collect();
+        assert_eq!(points, vec![(-2, 5), (-1, 3), (0, 1), (1, -1)]);
+        let points: Vec<Point> = rasterise(((3, -4), (3, -4))).collect();
+        assert_eq!(points, vec![(3, -4)]);
+        assert_eq!(rasterise(((0, 0), (5, 7))).count(), 2);
+        assert_eq!(rasterise(((9, 4), (3, 4))).count(), 7);
+    }
+
+    #[test]
+    fn test_puzzle_lines() {
+        let points: Vec<Point> = rasterise(((1, 1), (1, 3))).collect();
+        assert_eq!(points, vec![(1, 1), (1, 2), (1, 3)]);
+        let points: Vec<Point> = rasterise(((9, 7), (7, 7))).collect();
+        assert_eq!(points, vec![(9, 7), (8, 7), (7, 7)]);
+        let points: Vec<Point> = rasterise(((1, 1), (3, 3))).collect();
+        assert_eq!(points, vec![(1, 1), (2, 2), (3, 3)]);
+        let points: Vec<Point> = rasterise(((9, 7), (7, 9))).collect();
+        assert_eq!(points, vec![(9, 7), (8, 8), (7, 9)]);
+
+        let input = parse_from_str(TEST_INPUT);
+        let overlaps = count_overlaps(&input);
+        assert_eq!(overlaps.overlapping(), 12);
+        assert_eq!(overlaps.max(), 3);
+        assert_eq!(overlaps.count((4, 4)), 3);
+        assert_eq!(overlaps.count((0, 9)), 2);
+    }
+
+    #[test]
+    fn test_dense_and_sparse_agree() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..20 {
+            let lines: Vec<Line> = (0..30)
+                .map(|_| {
+                    let start = (rng.gen_range(-20..20), rng.gen_range(-20..20));
+                    let (run, rise) = (rng.gen_range(-4..=4), rng.gen_range(-4..=4));
+                    let length = rng.gen_range(0..6);
+                    (start, (start.0 + run * length, start.1 + rise * length))
+                })
+                .collect();
+            let mut dense = Overlaps::dense(Bounds::of(lines.iter().flat_map(|(start, end)| [*start, *end])).unwrap());
+            let mut sparse = Overlaps::sparse();
+            for point in lines.iter().flat_map(|line| rasterise(*line)) {
+                dense.add(point);
+                sparse.add(point);
+            }
+            assert_eq!(dense.overlapping(), sparse.overlapping());
+            assert_eq!(dense.max(), sparse.max());
+            for y in -45..45 {
+                for x in -45..45 {
+                    assert_eq!(dense.count((x, y)), sparse.count((x, y)));
+                }
+            }
+        }
+
+        let far_apart = [((0, 0), (0, 1)), ((100_000, -100_000), (100_000, -99_999))];
+        assert!(matches!(Overlaps::for_lines(&far_apart), Overlaps::Sparse(_)));
+        assert!(matches!(Overlaps::for_lines(&far_apart[..1]), Overlaps::Dense { .. }));
+        assert_eq!(count_overlaps(&far_apart).bounds().unwrap().width(), 100_001);
+
+        let furthest_apart = [
+            ((-(1 << 62), 0), (-(1 << 62), 2)),
+            ((1 << 62, 1), (1 << 62, -1)),
+            ((1 << 62, 0), (1 << 62, 0)),
+        ];
+        let bounds = Bounds::of(furthest_apart.iter().map(|(start, _)| *start)).unwrap();
+        assert_eq!(bounds.area(), None);
+        let overlaps = count_overlaps(&furthest_apart);
+        assert!(matches!(overlaps, Overlaps::Sparse(_)));
+        assert_eq!(overlaps.overlapping(), 1);
+        assert!(write_pgm(&mut vec![], &overlaps).is_err());
+    }
+
+    #[test]
+    fn test_pgm() {
+        let overlaps = count_overlaps(&[((-1, 0), (1, 0)), ((0, -1), (0, 0))]);
+        let mut out = vec![];
+        write_pgm(&mut out, &overlaps).unwrap();
+        let header = b"P5\n3 2\n255\n";
+        assert_eq!(&out[..header.len()], header);
+        assert_eq!(&out[header.len()..], &[0, 127, 0, 127, 255, 127]);
+
+        let mut out = vec![];
+        write_pgm(&mut out, &Overlaps::sparse()).unwrap();
+        assert_eq!(out, b"P5\n0 0\n255\n");
+
+        let far_apart = count_overlaps(&[((0, 0), (0, 1)), ((100_000, -100_000), (100_000, -99_999))]);
+        let mut out = vec![];
+        assert!(write_pgm(&mut out, &far_apart).is_err());
+        assert!(out.is_empty());
     }
 }
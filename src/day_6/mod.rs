@@ -1,7 +1,7 @@
-use counter::Counter;
-
-use std::collections::HashMap;
+use crate::helpers::matrix::Matrix;
+use num::traits::{One, Zero};
 use std::fs;
+use std::ops::{Add, Mul};
 use std::path::Path;
 
 type Input = Vec<usize>;
@@ -35,28 +35,124 @@ pub fn part_two(input: Input) -> usize {
     count_fish_after(&input, 256)
 }
 
-fn iterate_dem_fishies(counts: &mut HashMap<usize, usize>) {
-    let initial_counts = counts.clone();
-    for n in 0usize..8 {
-        *counts.get_mut(&n).unwrap() = *initial_counts.get(&(n + 1)).unwrap();
+/// How fish breed: each one makes a new fish every `cycle` days, and newborns take `maturation` more days
+/// than that before their first. A fish's timer counts down the days until it next breeds.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Species {
+    cycle: usize,
+    maturation: usize,
+}
+
+impl Species {
+    pub const LANTERNFISH: Species = Species {
+        cycle: 7,
+        maturation: 2,
+    };
+
+    #[allow(dead_code)]
+    pub fn new(cycle: usize, maturation: usize) -> Self {
+        if cycle == 0 {
+            panic!("You wot? Fish can't breed every 0 days");
+        }
+        Species { cycle, maturation }
+    }
+
+    /// The number of different timer values.
+    pub fn timers(&self) -> usize {
+        self.cycle + self.maturation
+    }
+
+    pub fn counts(&self, fish: &[usize]) -> Vec<u64> {
+        let mut counts = vec![0; self.timers()];
+        for timer in fish {
+            match counts.get_mut(*timer) {
+                Some(count) => *count += 1,
+                None => panic!("You wot? A timer of {} is too long", timer),
+            }
+        }
+        counts
+    }
+
+    /// Entry (i, j) is how many fish with timer i one fish with timer j becomes the next day.
+    fn step_matrix<T: Clone + Zero + One>(&self) -> Matrix<T> {
+        let mut matrix = Matrix::zero(self.timers());
+        for timer in 1..self.timers() {
+            matrix.values[timer - 1][timer] = T::one();
+        }
+        matrix.values[self.cycle - 1][0] = T::one();
+        let newborn = self.timers() - 1;
+        matrix.values[newborn][0] = matrix.values[newborn][0].clone() + T::one();
+        matrix
+    }
+
+    /// The number of fish after `days`, in logarithmic time by squaring the matrix of a day's breeding.
+    pub fn population_after<T>(&self, fish: &[usize], days: u64) -> T
+    where
+        T: Clone + Zero + One + From<u64>,
+    {
+        let matrix = self.step_matrix::<T>().pow(days);
+        let counts = self.counts(fish);
+        let mut population = T::zero();
+        for row in &matrix.values {
+            for (entry, count) in row.iter().zip(&counts) {
+                population = population + entry.clone() * T::from(*count);
+            }
+        }
+        population
+    }
+}
+
+/// Integers modulo `P`, for populations far too big to hold.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Modular<const P: u64>(pub u64);
+
+impl<const P: u64> From<u64> for Modular<P> {
+    fn from(n: u64) -> Self {
+        Modular(n % P)
     }
-    // the 6 and the 8 are special fishies
-    *counts.get_mut(&6).unwrap() = *initial_counts.get(&0).unwrap() + *initial_counts.get(&7).unwrap();
-    *counts.get_mut(&8).unwrap() = *initial_counts.get(&0).unwrap();
 }
 
-fn count_fish_after(input: &[usize], days: usize) -> usize {
-    let initial_counts: Counter<usize> = input.iter().copied().collect();
-    let mut counts: HashMap<usize, usize> = (0..9).map(|n| (n, *initial_counts.get(&n).unwrap_or(&0))).collect();
-    for _ in 0..days {
-        iterate_dem_fishies(&mut counts);
+impl<const P: u64> Add for Modular<P> {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Modular(((self.0 as u128 + other.0 as u128) % P as u128) as u64)
+    }
+}
+
+impl<const P: u64> Mul for Modular<P> {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        Modular(((self.0 as u128 * other.0 as u128) % P as u128) as u64)
+    }
+}
+
+impl<const P: u64> Zero for Modular<P> {
+    fn zero() -> Self {
+        Modular(0)
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl<const P: u64> One for Modular<P> {
+    fn one() -> Self {
+        Modular(1 % P)
     }
-    counts.values().sum()
+}
+
+fn count_fish_after(input: &[usize], days: usize) -> usize {
+    Species::LANTERNFISH.population_after::<u64>(input, days as u64) as usize
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use num::BigUint;
     const TEST_INPUT: [usize; 5] = [3, 4, 3, 1, 2];
 
     #[test]
@@ -73,4 +169,57 @@ mod tests {
         assert_eq!(count_fish_after(&TEST_INPUT, 15), 20);
         assert_eq!(count_fish_after(&TEST_INPUT, 18), 26);
     }
+
+    #[test]
+    fn test_part_two() {
+        assert_eq!(count_fish_after(&TEST_INPUT, 80), 5934);
+        assert_eq!(count_fish_after(&TEST_INPUT, 256), 26984457539);
+    }
+
+    fn simulate(species: Species, fish: &[usize], days: usize) -> usize {
+        let mut fish = fish.to_vec();
+        for _ in 0..days {
+            let newborns = fish.iter().filter(|timer| **timer == 0).count();
+            for timer in fish.iter_mut() {
+                *timer = if *timer == 0 { species.cycle - 1 } else { *timer - 1 };
+            }
+            fish.extend(vec![species.timers() - 1; newborns]);
+        }
+        fish.len()
+    }
+
+    #[test]
+    fn test_other_species() {
+        for (cycle, maturation) in [(1, 0), (2, 0), (3, 1), (4, 5), (7, 2)] {
+            let species = Species::new(cycle, maturation);
+            let fish: Vec<usize> = (0..species.timers()).collect();
+            for days in [0, 1, 5, 12] {
+                assert_eq!(
+                    species.population_after::<u64>(&fish, days as u64),
+                    simulate(species, &fish, days) as u64
+                );
+            }
+        }
+        assert_eq!(Species::new(1, 0).population_after::<u64>(&[0], 10), 1024);
+    }
+
+    #[test]
+    #[should_panic(expected = "You wot?")]
+    fn test_no_cycle() {
+        Species::new(0, 2);
+    }
+
+    #[test]
+    fn test_huge_populations() {
+        const P: u64 = 1_000_000_007;
+        let species = Species::LANTERNFISH;
+        let exact: BigUint = species.population_after(&TEST_INPUT, 10_000);
+        let modular: Modular<P> = species.population_after(&TEST_INPUT, 10_000);
+        assert_eq!(exact.clone() % P, BigUint::from(modular.0));
+        let small: BigUint = species.population_after(&TEST_INPUT, 256);
+        assert_eq!(small, BigUint::from(26984457539u64));
+
+        let modular: Modular<P> = species.population_after(&TEST_INPUT, 1_000_000_000_000);
+        assert!(modular.0 < P);
+    }
 }
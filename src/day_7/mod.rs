@@ -4,13 +4,15 @@ use std::path::Path;
 type Input = Vec<usize>;
 pub fn solve_part_1() -> Result<(), ()> {
     let input = parse_from_file("./inputs/day7.txt");
-    println!("Solution: {}", part_one(&input));
+    let alignment = part_one(&input);
+    println!("Solution: {} (at position {})", alignment.fuel, alignment.position);
     Ok(())
 }
 
 pub fn solve_part_2() -> Result<(), ()> {
     let input = parse_from_file("./inputs/day7.txt");
-    println!("Solution: {}", part_two(&input));
+    let alignment = part_two(&input);
+    println!("Solution: {} (at position {})", alignment.fuel, alignment.position);
     Ok(())
 }
 
@@ -23,55 +25,161 @@ fn parse_from_str(input: &str) -> Input {
     input.split(',').map(|n| n.parse().unwrap()).collect()
 }
 
-fn calculate_cost(position_to_check: usize, crab_positions: &[usize]) -> usize {
-    crab_positions
-        .iter()
-        .copied()
-        .map(|crab_position| crab_position.abs_diff(position_to_check))
-        .sum::<usize>()
+/// How much fuel a crab burns to move a given distance.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy)]
+pub enum Cost {
+    Linear,
+    Triangular,
+    Quadratic,
+    /// Any cost that grows at least as fast with each extra step as it did with the last.
+    Convex(fn(usize) -> usize),
+}
+
+impl Cost {
+    pub fn fuel(&self, distance: usize) -> usize {
+        match self {
+            Cost::Linear => distance,
+            Cost::Triangular => distance * (distance + 1) / 2,
+            Cost::Quadratic => distance * distance,
+            Cost::Convex(cost) => cost(distance),
+        }
+    }
+
+    /// The cheapest strategy that is sure to find the best position for this cost.
+    pub fn strategy(&self) -> Strategy {
+        match self {
+            Cost::Linear => Strategy::Median,
+            Cost::Triangular | Cost::Quadratic => Strategy::MeanNeighbourhood,
+            Cost::Convex(_) => Strategy::Ternary,
+        }
+    }
 }
 
-fn calculate_bigger_cost(position_to_check: usize, crab_positions: &[usize]) -> usize {
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Strategy {
+    /// Exact for linear costs, where half the crabs are always on either side of the best position.
+    Median,
+    /// Exact for triangular and quadratic costs, whose best position is within a half of the mean.
+    MeanNeighbourhood,
+    /// Works for any convex cost, as the total fuel is convex too. Over the integers this bisects on the
+    /// fuel's slope, which does the same job as splitting the range in three.
+    Ternary,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Alignment {
+    pub position: usize,
+    pub fuel: usize,
+}
+
+pub fn total_fuel(crab_positions: &[usize], position: usize, cost: &Cost) -> usize {
     crab_positions
         .iter()
-        .copied()
-        .map(|crab_position| {
-            let diff = crab_position.abs_diff(position_to_check);
-            (diff * (diff + 1)) / 2
-        })
-        .sum::<usize>()
+        .map(|crab_position| cost.fuel(crab_position.abs_diff(position)))
+        .sum()
 }
 
-pub fn part_one(crab_positions: &[usize]) -> usize {
+/// Finds the cheapest position using `strategy`, which is only sure to be the best if it suits the cost.
+pub fn align(crab_positions: &[usize], cost: &Cost, strategy: Strategy) -> Alignment {
     let min = crab_positions.iter().copied().min().unwrap();
     let max = crab_positions.iter().copied().max().unwrap();
-    (min..=max)
-        .map(|position| calculate_cost(position, crab_positions))
-        .min()
+    let candidates = match strategy {
+        Strategy::Median => {
+            let mut sorted = crab_positions.to_vec();
+            sorted.sort_unstable();
+            vec![sorted[(sorted.len() - 1) / 2]]
+        }
+        Strategy::MeanNeighbourhood => {
+            let mean = crab_positions.iter().sum::<usize>() / crab_positions.len();
+            (mean.saturating_sub(1).max(min)..=(mean + 1).min(max)).collect()
+        }
+        Strategy::Ternary => {
+            let (mut low, mut high) = (min, max);
+            while low < high {
+                let middle = low + (high - low) / 2;
+                if total_fuel(crab_positions, middle + 1, cost) < total_fuel(crab_positions, middle, cost) {
+                    low = middle + 1;
+                } else {
+                    high = middle;
+                }
+            }
+            vec![low]
+        }
+    };
+    candidates
+        .into_iter()
+        .map(|position| Alignment {
+            position,
+            fuel: total_fuel(crab_positions, position, cost),
+        })
+        .min_by_key(|alignment| (alignment.fuel, alignment.position))
         .unwrap()
 }
 
-pub fn part_two(crab_positions: &[usize]) -> usize {
-    let min = crab_positions.iter().copied().min().unwrap();
-    let max = crab_positions.iter().copied().max().unwrap();
-    (min..=max)
-        .map(|position| calculate_bigger_cost(position, crab_positions))
-        .min()
-        .unwrap()
+pub fn part_one(crab_positions: &[usize]) -> Alignment {
+    align(crab_positions, &Cost::Linear, Cost::Linear.strategy())
+}
+
+pub fn part_two(crab_positions: &[usize]) -> Alignment {
+    align(crab_positions, &Cost::Triangular, Cost::Triangular.strategy())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::Rng;
     const TEST_INPUT: [usize; 10] = [16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
 
     #[test]
     fn test_part_one() {
-        assert_eq!(part_one(&TEST_INPUT), 37);
+        assert_eq!(part_one(&TEST_INPUT).fuel, 37);
     }
 
     #[test]
     fn test_part_two() {
-        assert_eq!(part_two(&TEST_INPUT), 168);
+        assert_eq!(part_two(&TEST_INPUT).fuel, 168);
+    }
+
+    #[test]
+    fn test_positions() {
+        assert_eq!(
+            align(&TEST_INPUT, &Cost::Linear, Strategy::Median),
+            Alignment { position: 2, fuel: 37 }
+        );
+        assert_eq!(
+            align(&TEST_INPUT, &Cost::Triangular, Strategy::MeanNeighbourhood),
+            Alignment { position: 5, fuel: 168 }
+        );
+        assert_eq!(total_fuel(&TEST_INPUT, 10, &Cost::Linear), 71);
+        assert_eq!(total_fuel(&TEST_INPUT, 2, &Cost::Triangular), 206);
+    }
+
+    fn brute_force(crab_positions: &[usize], cost: &Cost) -> usize {
+        let max = crab_positions.iter().copied().max().unwrap();
+        (0..=max)
+            .map(|position| total_fuel(crab_positions, position, cost))
+            .min()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_strategies_against_brute_force() {
+        let mut rng = rand::thread_rng();
+        let costs = [
+            Cost::Linear,
+            Cost::Triangular,
+            Cost::Quadratic,
+            Cost::Convex(|distance| distance * distance * distance),
+            Cost::Convex(|distance| distance.saturating_sub(3)),
+        ];
+        for _ in 0..50 {
+            let crabs: Vec<usize> = (0..rng.gen_range(1..30)).map(|_| rng.gen_range(0..100)).collect();
+            for cost in &costs {
+                let best = brute_force(&crabs, cost);
+                assert_eq!(align(&crabs, cost, cost.strategy()).fuel, best);
+                assert_eq!(align(&crabs, cost, Strategy::Ternary).fuel, best);
+            }
+        }
     }
 }
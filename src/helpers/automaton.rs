@@ -0,0 +1,328 @@
+use crate::helpers::render::Frame;
+use bitvec::prelude::*;
+use std::fmt::{Display, Formatter};
+
+/// What each cell becomes, looked up by the 9 bit number read from its 3x3 neighbourhood, top left first.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Rule {
+    table: BitVec,
+}
+
+#[allow(dead_code)]
+impl Rule {
+    pub fn from_table(table: BitVec) -> Result<Self, String> {
+        if table.len() == 512 {
+            Ok(Rule { table })
+        } else {
+            Err(format!("A rule needs 512 entries, not {}", table.len()))
+        }
+    }
+
+    /// The day 20 form: 512 `#`s and `.`s.
+    pub fn from_algorithm(algorithm: &str) -> Result<Self, String> {
+        let table = algorithm
+            .trim()
+            .chars()
+            .map(|c| match c {
+                '#' => Ok(true),
+                '.' => Ok(false),
+                _ => Err(format!("{} isn't # or .", c)),
+            })
+            .collect::<Result<BitVec, String>>()?;
+        Rule::from_table(table)
+    }
+
+    /// A Life-like rule such as `B3/S23`, giving the neighbour counts that bring a dead cell to life and
+    /// those that keep a live cell alive.
+    pub fn life_like(rule: &str) -> Result<Self, String> {
+        let mut born = [false; 9];
+        let mut survive = [false; 9];
+        let parts: Vec<&str> = rule.trim().split('/').collect();
+        if parts.len() != 2 {
+            return Err(format!("{} should look like B3/S23", rule));
+        }
+        for part in parts {
+            let mut chars = part.chars();
+            let counts = match chars.next() {
+                Some('B' | 'b') => &mut born,
+                Some('S' | 's') => &mut survive,
+                _ => return Err(format!("{} should start with B or S", part)),
+            };
+            for c in chars {
+                match c.to_digit(10) {
+                    Some(count) if count <= 8 => counts[count as usize] = true,
+                    _ => return Err(format!("{} isn't a neighbour count", c)),
+                }
+            }
+        }
+        let table = (0..512usize)
+            .map(|index| {
+                let alive = index & 0b000_010_000 != 0;
+                let neighbours = (index & 0b111_101_111).count_ones() as usize;
+                if alive {
+                    survive[neighbours]
+                } else {
+                    born[neighbours]
+                }
+            })
+            .collect();
+        Rule::from_table(table)
+    }
+
+    /// Takes either of the string forms.
+    pub fn parse(rule: &str) -> Result<Self, String> {
+        if rule.contains('/') {
+            Rule::life_like(rule)
+        } else {
+            Rule::from_algorithm(rule)
+        }
+    }
+
+    pub fn next(&self, index: usize) -> bool {
+        self.table[index]
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let algorithm: String = self.table.iter().map(|bit| if *bit { '#' } else { '.' }).collect();
+        write!(f, "{}", algorithm)
+    }
+}
+
+/// A finite patch of live and dead cells, with every cell outside it set to `background`. The patch's top
+/// left cell is at `origin`, so cells keep their positions as the patch grows and shrinks.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Plane {
+    width: usize,
+    height: usize,
+    origin: (isize, isize),
+    cells: BitVec,
+    background: bool,
+}
+
+#[allow(dead_code)]
+impl Plane {
+    pub fn from_rows(rows: &[Vec<bool>]) -> Self {
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        Plane {
+            width,
+            height: rows.len(),
+            origin: (0, 0),
+            cells: rows
+                .iter()
+                .flat_map(|row| (0..width).map(move |x| row.get(x).copied().unwrap_or(false)))
+                .collect(),
+            background: false,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn origin(&self) -> (isize, isize) {
+        self.origin
+    }
+
+    pub fn background(&self) -> bool {
+        self.background
+    }
+
+    /// Whether the cell is alive, where the patch starts at (0, 0) and anything else is the background.
+    pub fn get(&self, y: isize, x: isize) -> bool {
+        if y < 0 || x < 0 || y >= self.height as isize || x >= self.width as isize {
+            self.background
+        } else {
+            self.cells[y as usize * self.width + x as usize]
+        }
+    }
+
+    /// The live cell count, or `None` if the background is alive and there are infinitely many.
+    pub fn alive(&self) -> Option<usize> {
+        if self.background {
+            None
+        } else {
+            Some(self.cells.count_ones())
+        }
+    }
+
+    /// The positions of the cells that differ from the background.
+    pub fn foreground(&self) -> Vec<(isize, isize)> {
+        let (origin_y, origin_x) = self.origin;
+        (0..self.height as isize)
+            .flat_map(|y| (0..self.width as isize).map(move |x| (y, x)))
+            .filter(|&(y, x)| self.get(y, x) != self.background)
+            .map(|(y, x)| (y + origin_y, x + origin_x))
+            .collect()
+    }
+
+    /// Every cell within one of the patch can change, so the patch grows by one on each side. The
+    /// background all has the same neighbourhood, so it becomes either the first or the last entry.
+    pub fn step(&self, rule: &Rule) -> Plane {
+        let (width, height) = (self.width + 2, self.height + 2);
+        let mut cells = BitVec::with_capacity(width * height);
+        for y in 0..height as isize {
+            let mut index = 0;
+            for x in -2..width as isize {
+                let column =
+                    (self.get(y - 2, x) as usize) << 6 | (self.get(y - 1, x) as usize) << 3 | self.get(y, x) as usize;
+                index = (index << 1) & 0b110_110_110 | column;
+                if x >= 0 {
+                    cells.push(rule.next(index));
+                }
+            }
+        }
+        Plane {
+            width,
+            height,
+            origin: (self.origin.0 - 1, self.origin.1 - 1),
+            cells,
+            background: rule.next(if self.background { 511 } else { 0 }),
+        }
+    }
+
+    /// Shrinks the patch down to the cells that differ from the background.
+    pub fn trim(&self) -> Plane {
+        let foreground = self.foreground();
+        let (origin_y, origin_x) = self.origin;
+        let top = foreground.iter().map(|(y, _)| *y).min().unwrap_or(origin_y);
+        let bottom = foreground.iter().map(|(y, _)| *y + 1).max().unwrap_or(origin_y);
+        let left = foreground.iter().map(|(_, x)| *x).min().unwrap_or(origin_x);
+        let right = foreground.iter().map(|(_, x)| *x + 1).max().unwrap_or(origin_x);
+        let cells = (top..bottom)
+            .flat_map(|y| (left..right).map(move |x| (y, x)))
+            .map(|(y, x)| self.get(y - origin_y, x - origin_x))
+            .collect();
+        Plane {
+            width: (right - left) as usize,
+            height: (bottom - top) as usize,
+            origin: (top, left),
+            cells,
+            background: self.background,
+        }
+    }
+
+    /// Draws the patch in the middle of a `width` by `height` frame, filling the rest with the background.
+    pub fn frame(&self, width: usize, height: usize) -> Frame {
+        let (offset_y, offset_x) = ((height - self.height) / 2, (width - self.width) / 2);
+        Frame::from_fn(width, height, |(y, x)| {
+            if self.get(y as isize - offset_y as isize, x as isize - offset_x as isize) {
+                ('#', [255, 255, 255])
+            } else {
+                ('.', [0, 0, 0])
+            }
+        })
+    }
+}
+
+impl Display for Plane {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for y in 0..self.height as isize {
+            let row: String = (0..self.width as isize)
+                .map(|x| if self.get(y, x) { '#' } else { '.' })
+                .collect();
+            writeln!(f, "{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads a pattern file: lines starting with `!` are comments, the first other line is the rule in either
+/// form, and the rest is the starting patch, with `#` or `O` for live cells and `.` for dead ones. Blank
+/// lines around the patch are ignored and short rows are padded with dead cells, so day 20's puzzle input
+/// is a pattern file too.
+pub fn parse_pattern(input: &str) -> Result<(Rule, Plane), String> {
+    let mut lines = input.lines().filter(|line| !line.starts_with('!'));
+    let rule = Rule::parse(lines.next().ok_or("The pattern has no rule")?)?;
+    let rows = lines
+        .map(|line| line.trim_end())
+        .skip_while(|line| line.is_empty())
+        .collect::<Vec<_>>();
+    let end = rows.iter().rposition(|line| !line.is_empty()).map_or(0, |i| i + 1);
+    let rows = rows[..end]
+        .iter()
+        .map(|line| {
+            line.chars()
+                .map(|c| match c {
+                    '#' | 'O' => Ok(true),
+                    '.' => Ok(false),
+                    _ => Err(format!("{} isn't a cell", c)),
+                })
+                .collect()
+        })
+        .collect::<Result<Vec<Vec<bool>>, String>>()?;
+    Ok((rule, Plane::from_rows(&rows)))
+}
+
+/// Writes a pattern file that `parse_pattern` reads back the same, apart from where the patch is.
+#[allow(dead_code)]
+pub fn write_pattern(rule: &Rule, plane: &Plane) -> String {
+    format!("{}\n\n{}", rule, plane)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLIDER: &str = "! A glider\nB3/S23\n\n.#.\n..#\n###\n";
+
+    #[test]
+    fn test_life_like() {
+        let life = Rule::life_like("B3/S23").unwrap();
+        assert!(life.next(0b111_000_000));
+        assert!(!life.next(0b111_000_001));
+        assert!(life.next(0b101_010_000));
+        assert!(life.next(0b111_010_000));
+        assert!(!life.next(0b111_010_011));
+        assert!(!life.next(0b000_010_000));
+        assert_eq!(Rule::life_like("S23/B3"), Ok(life.clone()));
+        assert_eq!(Rule::parse("b3/s23"), Ok(life));
+        assert!(Rule::life_like("B9/S23").is_err());
+        assert!(Rule::life_like("B3").is_err());
+        assert!(Rule::from_algorithm("#.#").is_err());
+    }
+
+    #[test]
+    fn test_glider() {
+        let (rule, mut plane) = parse_pattern(GLIDER).unwrap();
+        let start = plane.foreground();
+        for _ in 0..4 {
+            plane = plane.step(&rule).trim();
+        }
+        let moved: Vec<(isize, isize)> = start.iter().map(|(y, x)| (y + 1, x + 1)).collect();
+        assert_eq!(plane.foreground(), moved);
+        assert_eq!(plane.to_string(), ".#.\n..#\n###\n");
+        assert_eq!(plane.origin(), (1, 1));
+    }
+
+    #[test]
+    fn test_blinker_and_background() {
+        let (rule, plane) = parse_pattern("B3/S23\n###").unwrap();
+        let once = plane.step(&rule).trim();
+        assert_eq!(once.to_string(), "#\n#\n#\n");
+        assert_eq!(once.step(&rule).trim(), plane);
+
+        let inverting = Rule::life_like("B012345678/S").unwrap();
+        let flipped = plane.step(&inverting);
+        assert!(flipped.background());
+        assert_eq!(flipped.alive(), None);
+        assert_eq!(flipped.trim().foreground().len(), 3);
+    }
+
+    #[test]
+    fn test_pattern_round_trip() {
+        let (rule, plane) = parse_pattern(GLIDER).unwrap();
+        assert_eq!(parse_pattern(&write_pattern(&rule, &plane)), Ok((rule.clone(), plane)));
+        assert_eq!(Rule::from_algorithm(&rule.to_string()), Ok(rule));
+
+        let (_, ragged) = parse_pattern("B3/S23\n\nO\n.O.\n\n").unwrap();
+        assert_eq!(ragged.to_string(), "#..\n.#.\n");
+        assert!(parse_pattern("B3/S23\n\n.x.").is_err());
+        assert!(parse_pattern("! nothing").is_err());
+    }
+}
@@ -0,0 +1,86 @@
+use num::traits::{One, Zero};
+
+/// A square matrix over anything with a zero and a one, for stepping linear recurrences by squaring.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Matrix<T> {
+    pub values: Vec<Vec<T>>,
+}
+
+impl<T: Clone + Zero + One> Matrix<T> {
+    pub fn zero(size: usize) -> Self {
+        Matrix {
+            values: vec![vec![T::zero(); size]; size],
+        }
+    }
+
+    pub fn identity(size: usize) -> Self {
+        let mut matrix = Matrix::zero(size);
+        for i in 0..size {
+            matrix.values[i][i] = T::one();
+        }
+        matrix
+    }
+
+    pub fn multiply(&self, other: &Matrix<T>) -> Matrix<T> {
+        let size = self.values.len();
+        let mut result: Matrix<T> = Matrix::zero(size);
+        for i in 0..size {
+            for k in 0..size {
+                if self.values[i][k].is_zero() {
+                    continue;
+                }
+                for j in 0..size {
+                    result.values[i][j] =
+                        result.values[i][j].clone() + self.values[i][k].clone() * other.values[k][j].clone();
+                }
+            }
+        }
+        result
+    }
+
+    /// The matrix times a column vector.
+    pub fn apply(&self, vector: &[T]) -> Vec<T> {
+        self.values
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(vector)
+                    .filter(|(entry, _)| !entry.is_zero())
+                    .fold(T::zero(), |sum, (entry, value)| sum + entry.clone() * value.clone())
+            })
+            .collect()
+    }
+
+    /// Squares once per bit of the exponent after the first, as the last square would never be used.
+    pub fn pow(&self, mut exponent: u64) -> Matrix<T> {
+        let mut result = Matrix::identity(self.values.len());
+        let mut square = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.multiply(&square);
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                square = square.multiply(&square);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fibonacci() {
+        let step = Matrix {
+            values: vec![vec![1u64, 1], vec![1, 0]],
+        };
+        assert_eq!(step.pow(0), Matrix::identity(2));
+        assert_eq!(step.pow(10).apply(&[1, 0]), vec![89, 55]);
+        assert_eq!(step.pow(3).multiply(&step.pow(4)), step.pow(7));
+        assert_eq!(step.pow(1), step);
+        assert_eq!(step.pow(92).apply(&[1, 0])[1], 7540113804746346429);
+    }
+}
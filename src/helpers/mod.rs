@@ -1,3 +1,7 @@
+pub mod automaton;
+pub mod matrix;
+pub mod render;
+
 use std::fs;
 use std::path::Path;
 
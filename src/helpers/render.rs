@@ -0,0 +1,389 @@
+use hashbrown::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs::{create_dir_all, File};
+use std::io::{self, stdout, BufWriter, Write};
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::Duration;
+
+pub type Colour = [u8; 3];
+
+/// One picture of a simulation: a glyph for the terminal and a colour for images at each cell.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Frame {
+    width: usize,
+    height: usize,
+    cells: Vec<(char, Colour)>,
+}
+
+#[allow(dead_code)]
+impl Frame {
+    pub fn from_fn<F>(width: usize, height: usize, cell: F) -> Self
+    where
+        F: Fn((usize, usize)) -> (char, Colour),
+    {
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (y, x)))
+            .map(cell)
+            .collect();
+        Frame { width, height, cells }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn glyph(&self, (y, x): (usize, usize)) -> char {
+        self.cells[y * self.width + x].0
+    }
+
+    pub fn colour(&self, (y, x): (usize, usize)) -> Colour {
+        self.cells[y * self.width + x].1
+    }
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for row in self.cells.chunks(self.width) {
+            writeln!(f, "{}", row.iter().map(|(glyph, _)| glyph).collect::<String>())?;
+        }
+        Ok(())
+    }
+}
+
+/// Where the frames of a simulation go.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Output {
+    /// Plays them in the terminal, redrawing over the previous frame.
+    Terminal { fps: f64 },
+    /// An animated GIF, with each cell drawn as a `scale` pixel square.
+    Gif { path: PathBuf, fps: f64, scale: usize },
+    /// A directory of numbered PPM images.
+    Ppm { directory: PathBuf, scale: usize },
+}
+
+pub fn render<I: IntoIterator<Item = Frame>>(frames: I, output: &Output) -> io::Result<()> {
+    match output {
+        Output::Terminal { fps } => play(&mut stdout(), frames, *fps),
+        Output::Gif { path, fps, scale } => {
+            let frames: Vec<Frame> = frames.into_iter().collect();
+            let delay = (100.0 / fps).round().max(2.0) as u16;
+            write_gif(&mut BufWriter::new(File::create(path)?), &frames, delay, *scale)
+        }
+        Output::Ppm { directory, scale } => {
+            create_dir_all(directory)?;
+            for (i, frame) in frames.into_iter().enumerate() {
+                let path = directory.join(format!("frame_{:04}.ppm", i));
+                write_ppm(&mut BufWriter::new(File::create(path)?), &frame, *scale)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Draws each frame over the last with ANSI escape codes, waiting between them to keep to `fps`.
+pub fn play<W: Write, I: IntoIterator<Item = Frame>>(out: &mut W, frames: I, fps: f64) -> io::Result<()> {
+    write!(out, "\x1b[2J")?;
+    for frame in frames {
+        write!(out, "\x1b[H")?;
+        for y in 0..frame.height {
+            let mut current = None;
+            for x in 0..frame.width {
+                let colour = frame.colour((y, x));
+                if current != Some(colour) {
+                    write!(out, "\x1b[38;2;{};{};{}m", colour[0], colour[1], colour[2])?;
+                    current = Some(colour);
+                }
+                write!(out, "{}", frame.glyph((y, x)))?;
+            }
+            writeln!(out, "\x1b[0m")?;
+        }
+        out.flush()?;
+        if fps > 0.0 {
+            sleep(Duration::from_secs_f64(1.0 / fps));
+        }
+    }
+    Ok(())
+}
+
+pub fn write_ppm<W: Write>(out: &mut W, frame: &Frame, scale: usize) -> io::Result<()> {
+    write!(out, "P6\n{} {}\n255\n", frame.width * scale, frame.height * scale)?;
+    for y in 0..frame.height * scale {
+        for x in 0..frame.width * scale {
+            out.write_all(&frame.colour((y / scale, x / scale)))?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes a looping GIF with `delay` hundredths of a second between frames, which all need to be the same
+/// size and use at most 256 colours between them.
+pub fn write_gif<W: Write>(out: &mut W, frames: &[Frame], delay: u16, scale: usize) -> io::Result<()> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidInput, message.to_string());
+    let first = frames.first().ok_or_else(|| invalid("There are no frames"))?;
+    if frames
+        .iter()
+        .any(|frame| frame.width != first.width || frame.height != first.height)
+    {
+        return Err(invalid("The frames aren't all the same size"));
+    }
+    let (width, height) = (first.width * scale, first.height * scale);
+    if width > u16::MAX as usize || height > u16::MAX as usize {
+        return Err(invalid("The frames are too big for a GIF"));
+    }
+
+    let mut palette: Vec<Colour> = vec![];
+    let mut indices: HashMap<Colour, u8> = HashMap::new();
+    for (_, colour) in frames.iter().flat_map(|frame| frame.cells.iter()) {
+        if !indices.contains_key(colour) {
+            if palette.len() == 256 {
+                return Err(invalid("The frames have more than 256 colours"));
+            }
+            indices.insert(*colour, palette.len() as u8);
+            palette.push(*colour);
+        }
+    }
+    let mut palette_bits = 1;
+    while 1 << palette_bits < palette.len() {
+        palette_bits += 1;
+    }
+
+    out.write_all(b"GIF89a")?;
+    out.write_all(&(width as u16).to_le_bytes())?;
+    out.write_all(&(height as u16).to_le_bytes())?;
+    out.write_all(&[0xf0 | (palette_bits - 1), 0, 0])?;
+    for i in 0..1 << palette_bits {
+        out.write_all(&palette.get(i).copied().unwrap_or([0, 0, 0]))?;
+    }
+    out.write_all(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00")?;
+
+    let min_code_size = palette_bits.max(2);
+    for frame in frames {
+        out.write_all(&[0x21, 0xf9, 0x04, 0x00])?;
+        out.write_all(&delay.to_le_bytes())?;
+        out.write_all(&[0x00, 0x00])?;
+        out.write_all(&[0x2c, 0, 0, 0, 0])?;
+        out.write_all(&(width as u16).to_le_bytes())?;
+        out.write_all(&(height as u16).to_le_bytes())?;
+        out.write_all(&[0x00, min_code_size])?;
+        let pixels: Vec<u8> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (y, x)))
+            .map(|(y, x)| indices[&frame.colour((y / scale, x / scale))])
+            .collect();
+        for block in lzw_encode(&pixels, min_code_size).chunks(255) {
+            out.write_all(&[block.len() as u8])?;
+            out.write_all(block)?;
+        }
+        out.write_all(&[0x00])?;
+    }
+    out.write_all(&[0x3b])
+}
+
+/// Packs codes of varying width into bytes, least significant bit first, as GIF wants.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    length: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u8) {
+        self.buffer |= (code as u32) << self.length;
+        self.length += size;
+        while self.length >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.length -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.length > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+/// GIF's variant of LZW: codes start one bit wider than the colour indices, grow as the table fills up,
+/// and the table is cleared before it goes past 12 bit codes.
+fn lzw_encode(pixels: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear: u16 = 1 << min_code_size;
+    let end = clear + 1;
+    let mut code_size = min_code_size + 1;
+    let mut next_code = end + 1;
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut writer = BitWriter {
+        bytes: vec![],
+        buffer: 0,
+        length: 0,
+    };
+    writer.write(clear, code_size);
+
+    let mut current: Option<u16> = None;
+    for &pixel in pixels {
+        let prefix = match current {
+            Some(prefix) => prefix,
+            None => {
+                current = Some(pixel as u16);
+                continue;
+            }
+        };
+        if let Some(&code) = table.get(&(prefix, pixel)) {
+            current = Some(code);
+            continue;
+        }
+        writer.write(prefix, code_size);
+        if next_code == 1 << code_size && code_size < 12 {
+            code_size += 1;
+        }
+        if next_code < 4095 {
+            table.insert((prefix, pixel), next_code);
+            next_code += 1;
+        } else {
+            writer.write(clear, code_size);
+            table.clear();
+            code_size = min_code_size + 1;
+            next_code = end + 1;
+        }
+        current = Some(pixel as u16);
+    }
+    if let Some(prefix) = current {
+        writer.write(prefix, code_size);
+        if next_code == 1 << code_size && code_size < 12 {
+            code_size += 1;
+        }
+    }
+    writer.write(end, code_size);
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    /// A straightforward GIF LZW decoder, to check the encoder against.
+    fn lzw_decode(bytes: &[u8], min_code_size: u8) -> Vec<u8> {
+        let clear = 1usize << min_code_size;
+        let end = clear + 1;
+        let mut code_size = min_code_size as usize + 1;
+        let mut table: Vec<Vec<u8>> = vec![];
+        let mut previous: Option<usize> = None;
+        let mut output = vec![];
+        let (mut buffer, mut length, mut bytes) = (0usize, 0usize, bytes.iter());
+        loop {
+            while length < code_size {
+                buffer |= (*bytes.next().unwrap() as usize) << length;
+                length += 8;
+            }
+            let code = buffer & ((1 << code_size) - 1);
+            buffer >>= code_size;
+            length -= code_size;
+
+            if code == clear {
+                table = (0..clear).map(|i| vec![i as u8]).chain(vec![vec![], vec![]]).collect();
+                code_size = min_code_size as usize + 1;
+                previous = None;
+                continue;
+            }
+            if code == end {
+                return output;
+            }
+            let entry = match previous {
+                None => table[code].clone(),
+                Some(previous) => {
+                    let entry = if code < table.len() {
+                        table[code].clone()
+                    } else {
+                        let mut entry = table[previous].clone();
+                        entry.push(table[previous][0]);
+                        entry
+                    };
+                    let mut added = table[previous].clone();
+                    added.push(entry[0]);
+                    table.push(added);
+                    entry
+                }
+            };
+            if table.len() == 1 << code_size && code_size < 12 {
+                code_size += 1;
+            }
+            output.extend(&entry);
+            previous = Some(code);
+        }
+    }
+
+    #[test]
+    fn test_lzw_round_trip() {
+        let mut rng = rand::thread_rng();
+        for (colours, length) in [(2, 10), (4, 1000), (4, 50_000), (16, 20_000), (256, 30_000)] {
+            let min_code_size = (colours as f64).log2().max(2.0) as u8;
+            let pixels: Vec<u8> = (0..length).map(|_| rng.gen_range(0..colours) as u8).collect();
+            assert_eq!(lzw_decode(&lzw_encode(&pixels, min_code_size), min_code_size), pixels);
+        }
+        let pixels = vec![3; 100_000];
+        assert_eq!(lzw_decode(&lzw_encode(&pixels, 2), 2), pixels);
+    }
+
+    fn checkerboard(offset: usize) -> Frame {
+        Frame::from_fn(3, 2, |(y, x)| {
+            if (y + x + offset) % 2 == 0 {
+                ('#', [255, 255, 255])
+            } else {
+                ('.', [0, 0, 0])
+            }
+        })
+    }
+
+    #[test]
+    fn test_frame() {
+        let frame = checkerboard(0);
+        assert_eq!(frame.to_string(), "#.#\n.#.\n");
+        assert_eq!(frame.colour((1, 1)), [255, 255, 255]);
+    }
+
+    #[test]
+    fn test_ppm() {
+        let mut out = vec![];
+        write_ppm(&mut out, &checkerboard(0), 2).unwrap();
+        let header = b"P6\n6 4\n255\n";
+        assert_eq!(&out[..header.len()], header);
+        assert_eq!(out.len(), header.len() + 6 * 4 * 3);
+        assert_eq!(
+            &out[header.len()..header.len() + 12],
+            &[255, 255, 255, 255, 255, 255, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_gif() {
+        let mut out = vec![];
+        write_gif(&mut out, &[checkerboard(0), checkerboard(1)], 10, 1).unwrap();
+        assert_eq!(&out[..6], b"GIF89a");
+        assert_eq!(&out[6..10], &[3, 0, 2, 0]);
+        assert_eq!(out[10], 0xf0);
+        assert_eq!(&out[13..19], &[255, 255, 255, 0, 0, 0]);
+        assert_eq!(out.last(), Some(&0x3b));
+        assert!(write_gif(
+            &mut vec![],
+            &[checkerboard(0), Frame::from_fn(1, 1, |_| ('.', [0; 3]))],
+            10,
+            1
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_play() {
+        let mut out = vec![];
+        play(&mut out, vec![checkerboard(0), checkerboard(1)], 0.0).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with("\x1b[2J\x1b[H\x1b[38;2;255;255;255m#\x1b[38;2;0;0;0m."));
+        assert_eq!(text.matches("\x1b[H").count(), 2);
+    }
+}
@@ -38,12 +38,117 @@ struct Args {
     #[argh(positional)]
     /// part of the puzzle to do.
     part: usize,
+
+    #[argh(option)]
+    /// save a PGM heatmap of where day 5's vent lines overlap.
+    heatmap: Option<PathBuf>,
+
+    #[argh(switch)]
+    /// let the diagonals count as lines in day 4's bingo.
+    diagonals: bool,
+
+    #[argh(switch)]
+    /// use the depth-first search instead of best-first for day 23.
+    dfs: bool,
+
+    #[argh(switch)]
+    /// print each move of the best solution for day 23, found by the best-first search.
+    replay: bool,
+
+    #[argh(option)]
+    /// write a day 13 input that folds up to spell this, using the folds from the puzzle input.
+    spell: Option<String>,
+
+    #[argh(switch)]
+    /// print the day 12 cave system in Graphviz DOT format.
+    dot: bool,
+
+    #[argh(switch)]
+    /// list the day 12 paths instead of counting them.
+    paths: bool,
+
+    #[argh(option)]
+    /// most day 12 paths to list.
+    limit: Option<usize>,
+
+    #[argh(switch)]
+    /// print the odds of the day 21 Dirac dice game.
+    stats: bool,
+
+    #[argh(option)]
+    /// starting spaces for the day 21 odds, like 4,8.
+    starts: Option<String>,
+
+    #[argh(switch)]
+    /// step through the day 24 program in an interactive debugger.
+    debug: bool,
+
+    #[argh(switch)]
+    /// print the day 24 program as pseudocode.
+    disassemble: bool,
+
+    #[argh(option)]
+    /// model number for the day 24 debugger to read its digits from.
+    digits: Option<String>,
+
+    #[argh(switch)]
+    /// play the simulation for day 11, 20 or 25 in the terminal.
+    animate: bool,
+
+    #[argh(option, default = "10.0")]
+    /// frames per second for animations.
+    fps: f64,
+
+    #[argh(option)]
+    /// save the simulation for day 11, 20 or 25 as an animated GIF.
+    gif: Option<PathBuf>,
+
+    #[argh(option)]
+    /// save the simulation for day 11, 20 or 25 as a directory of PPM images.
+    ppm: Option<PathBuf>,
+
+    #[argh(option, default = "4")]
+    /// size in pixels of each cell in saved images.
+    scale: usize,
 }
 
-use anyhow::Result;
+use anyhow::{ensure, Result};
+use helpers::render::Output;
+use std::path::PathBuf;
 fn main() -> Result<()> {
     let args: Args = argh::from_env();
     const FAILURE_TEXT: &str = "Failed to find the answer";
+    ensure!(
+        !(args.dfs && args.replay),
+        "Only the best-first search keeps its moves, so --replay can't be used with --dfs"
+    );
+    let search = if args.dfs {
+        day_23::Search::DepthFirst
+    } else {
+        day_23::Search::BestFirst
+    };
+    let output = match (args.gif, args.ppm) {
+        (Some(path), _) => Some(Output::Gif {
+            path,
+            fps: args.fps,
+            scale: args.scale,
+        }),
+        (None, Some(directory)) => Some(Output::Ppm {
+            directory,
+            scale: args.scale,
+        }),
+        (None, None) if args.animate => Some(Output::Terminal { fps: args.fps }),
+        (None, None) => None,
+    };
+    if let Some(output) = output {
+        match args.day {
+            11 => day_11::animate(&output).expect(FAILURE_TEXT),
+            20 => day_20::animate(&output).expect(FAILURE_TEXT),
+            25 => day_25::animate(&output).expect(FAILURE_TEXT),
+            _ => unimplemented!("This day don't animate, brah."),
+        };
+        return Ok(());
+    }
     match (args.day, args.part) {
         (1, 1) => day_1::solve_part_1().expect(FAILURE_TEXT),
         (1, 2) => day_1::solve_part_2().expect(FAILURE_TEXT),
@@ -51,8 +156,9 @@ fn main() -> Result<()> {
         (2, 2) => day_2::solve_part_2().expect(FAILURE_TEXT),
         (3, 1) => day_3::solve_part_1().expect(FAILURE_TEXT),
         (3, 2) => day_3::solve_part_2().expect(FAILURE_TEXT),
-        (4, 1) => day_4::solve_part_1().expect(FAILURE_TEXT),
-        (4, 2) => day_4::solve_part_2().expect(FAILURE_TEXT),
+        (4, 1) => day_4::solve_part_1(args.diagonals).expect(FAILURE_TEXT),
+        (4, 2) => day_4::solve_part_2(args.diagonals).expect(FAILURE_TEXT),
+        (5, part) if args.heatmap.is_some() => day_5::save_heatmap(part, &args.heatmap.unwrap()).expect(FAILURE_TEXT),
         (5, 1) => day_5::solve_part_1().expect(FAILURE_TEXT),
         (5, 2) => day_5::solve_part_2().expect(FAILURE_TEXT),
         (6, 1) => day_6::solve_part_1().expect(FAILURE_TEXT),
@@ -67,8 +173,11 @@ fn main() -> Result<()> {
         (10, 2) => day_10::solve_part_2().expect(FAILURE_TEXT),
         (11, 1) => day_11::solve_part_1().expect(FAILURE_TEXT),
         (11, 2) => day_11::solve_part_2().expect(FAILURE_TEXT),
+        (12, _) if args.dot => day_12::print_dot().expect(FAILURE_TEXT),
+        (12, part) if args.paths => day_12::list_paths(part, args.limit).expect(FAILURE_TEXT),
         (12, 1) => day_12::solve_part_1().expect(FAILURE_TEXT),
         (12, 2) => day_12::solve_part_2().expect(FAILURE_TEXT),
+        (13, _) if args.spell.is_some() => day_13::print_puzzle(&args.spell.unwrap()).expect(FAILURE_TEXT),
         (13, 1) => day_13::solve_part_1().expect(FAILURE_TEXT),
         (13, 2) => day_13::solve_part_2().expect(FAILURE_TEXT),
         (14, 1) => day_14::solve_part_1().expect(FAILURE_TEXT),
@@ -85,12 +194,15 @@ fn main() -> Result<()> {
         (19, 2) => day_19::solve_part_2().expect(FAILURE_TEXT),
         (20, 1) => day_20::solve_part_1().expect(FAILURE_TEXT),
         (20, 2) => day_20::solve_part_2().expect(FAILURE_TEXT),
+        (21, _) if args.stats => day_21::print_stats(args.starts).expect(FAILURE_TEXT),
         (21, 1) => day_21::solve_part_1().expect(FAILURE_TEXT),
         (21, 2) => day_21::solve_part_2().expect(FAILURE_TEXT),
         (22, 1) => day_22::solve_part_1().expect(FAILURE_TEXT),
         (22, 2) => day_22::solve_part_2().expect(FAILURE_TEXT),
-        (23, 1) => day_23::solve_part_1().expect(FAILURE_TEXT),
-        (23, 2) => day_23::solve_part_2().expect(FAILURE_TEXT),
+        (23, 1) => day_23::solve_part_1(search, args.replay).expect(FAILURE_TEXT),
+        (23, 2) => day_23::solve_part_2(search, args.replay).expect(FAILURE_TEXT),
+        (24, _) if args.debug => day_24::debug(args.digits).expect(FAILURE_TEXT),
+        (24, _) if args.disassemble => day_24::print_disassembly().expect(FAILURE_TEXT),
         (24, 1) => day_24::solve_part_1().expect(FAILURE_TEXT),
         (24, 2) => day_24::solve_part_2().expect(FAILURE_TEXT),
         (25, 1) => day_25::solve_part_1().expect(FAILURE_TEXT),